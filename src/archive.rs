@@ -0,0 +1,176 @@
+//! Reading tzdata directly from a release archive.
+//!
+//! tzdata releases are distributed as gzipped tarballs, such as
+//! `tzdata2024a.tar.gz`, with the data files at the top level of the
+//! archive. Rather than unpacking one of these to disk first, this module
+//! reads the member files straight out of the archive and builds them into
+//! a `Table`, using the same set of files as `TzdataLoader::load_dir`.
+//!
+//! Archives also contain a `version` file naming the release they came
+//! from, which gets returned alongside the table.
+//!
+//! This module is only available with the `tarball` feature enabled.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use parse_zoneinfo::files::TzdataLoader;
+//!
+//! let release = TzdataLoader::new().load_archive("tzdata2024a.tar.gz").unwrap();
+//! assert_eq!(release.version.as_ref().map(|v| &**v), Some("2024a"));
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use tar::Archive;
+
+use files::{Error, TzdataLoader};
+use table::Table;
+
+
+/// A table built from a tzdata archive, along with the release it was
+/// built from.
+#[derive(PartialEq, Debug)]
+pub struct TzdataRelease {
+
+    /// The contents of the archive’s `version` file, such as “2024a”, or
+    /// `None` if the archive didn’t have one.
+    pub version: Option<String>,
+
+    /// The table built from the archive’s data files.
+    pub table: Table,
+}
+
+impl TzdataLoader {
+
+    /// Reads every file in the list out of the gzipped tarball at the given
+    /// path, and builds them into a single table.
+    pub fn load_archive<P: AsRef<Path>>(&self, path: P) -> Result<TzdataRelease, Error> {
+        let path = path.as_ref();
+        match File::open(path) {
+            Ok(file) => self.load_archive_reader(&path.display().to_string(), file),
+            Err(e)   => Err(Error::Io(path.display().to_string(), e)),
+        }
+    }
+
+    /// Reads every file in the list out of a gzipped tarball read from the
+    /// given reader, and builds them into a single table. The archive name
+    /// is only used to report the locations of errors.
+    ///
+    /// Members are matched by their file name, so data files inside a
+    /// top-level directory in the archive are found as well.
+    pub fn load_archive_reader<R: Read>(&self, archive_name: &str, reader: R) -> Result<TzdataRelease, Error> {
+        let mut archive = Archive::new(GzDecoder::new(reader));
+        let mut contents = HashMap::new();
+        let mut version = None;
+
+        let entries = match archive.entries() {
+            Ok(entries) => entries,
+            Err(e)      => return Err(Error::Io(archive_name.to_owned(), e)),
+        };
+
+        for entry in entries {
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(e)    => return Err(Error::Io(archive_name.to_owned(), e)),
+            };
+
+            let name = match entry.path() {
+                Ok(path) => match path.file_name().and_then(|n| n.to_str()) {
+                    Some(name) => name.to_owned(),
+                    None       => continue,
+                },
+                Err(e) => return Err(Error::Io(archive_name.to_owned(), e)),
+            };
+
+            if name != "version" && !self.files().contains(&name) {
+                continue;
+            }
+
+            let mut text = String::new();
+            if let Err(e) = entry.read_to_string(&mut text) {
+                return Err(Error::Io(format!("{}/{}", archive_name, name), e));
+            }
+
+            if name == "version" {
+                version = Some(text.trim().to_owned());
+            }
+            else {
+                let _ = contents.insert(name, text);
+            }
+        }
+
+        let mut sources = Vec::new();
+        for file in self.files() {
+            let name = format!("{}/{}", archive_name, file);
+            match contents.remove(file) {
+                Some(text) => sources.push((name, text)),
+                None       => {
+                    let e = io::Error::new(io::ErrorKind::NotFound, "file not found in archive");
+                    return Err(Error::Io(name, e));
+                },
+            }
+        }
+
+        Ok(TzdataRelease {
+            version,
+            table: self.build_sources(&sources)?,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use files::TZDATA_FILES;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tar::{Builder, Header};
+
+    fn archive(members: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for &(name, contents) in members {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, contents.as_bytes()).unwrap();
+        }
+
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn release() {
+        let mut members = vec![
+            ("version", "2024a\n"),
+            ("README", "Not a data file"),
+            ("europe", "Zone\tEurope/Lisbon\t-0:36:45 -\tLMT\n"),
+            ("backward", "Link\tEurope/Lisbon\tPortugal\n"),
+        ];
+        for file in TZDATA_FILES {
+            if !members.iter().any(|&(name, _)| name == *file) {
+                members.push((file, ""));
+            }
+        }
+
+        let release = TzdataLoader::new().load_archive_reader("tzdata.tar.gz", &*archive(&members)).unwrap();
+        assert_eq!(release.version, Some("2024a".to_owned()));
+        assert_eq!(release.table.zonesets["Europe/Lisbon"].len(), 1);
+        assert_eq!(release.table.links["Portugal"], "Europe/Lisbon");
+    }
+
+    #[test]
+    fn missing_file() {
+        let data = archive(&[("europe", "")]);
+        match TzdataLoader::new().load_archive_reader("tzdata.tar.gz", &*data) {
+            Err(Error::Io(name, _)) => assert_eq!(name, "tzdata.tar.gz/africa"),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! A single error type covering everything that can go wrong in this crate.
+//!
+//! Each module has its own error type, which is the most specific thing to
+//! match against. When errors from several modules need to be passed around
+//! together, such as from a function that loads a table and then writes out
+//! TZif files for it, they can all be converted into this type with `?`.
+//!
+//! All the payloads are owned, so this type is `Send`, `Sync`, and
+//! `'static`, and can be turned into a `Box<dyn Error + Send + Sync>`.
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::io;
+
+use files;
+use leap;
+use line;
+use metadata;
+use posix;
+use table;
+use transitions;
+
+
+/// Any error produced by this crate.
+///
+/// This is transparent: its `Display` and `source` are the same as those of
+/// the error it wraps.
+#[derive(Debug)]
+pub enum Error {
+
+    /// An error reading, parsing, or building a set of files.
+    Files(files::Error),
+
+    /// An error parsing a single line.
+    Line(line::Error),
+
+    /// An error adding a line to a table, or building it.
+    Table(table::Error),
+
+    /// An error adding a line to a leap second table.
+    Leap(leap::Error),
+
+    /// An error parsing a line of a metadata file.
+    Metadata(metadata::Error),
+
+    /// A zone’s timespans couldn’t be computed.
+    Transitions(transitions::Error),
+
+    /// A zone couldn’t be expressed as a POSIX TZ string.
+    TzString(posix::Error),
+
+    /// An error writing output, such as a TZif file.
+    Io(io::Error),
+}
+
+impl Error {
+    fn inner(&self) -> &(dyn ErrorTrait + Send + Sync + 'static) {
+        match *self {
+            Error::Files(ref e)       => e,
+            Error::Line(ref e)        => e,
+            Error::Table(ref e)       => e,
+            Error::Leap(ref e)        => e,
+            Error::Metadata(ref e)    => e,
+            Error::Transitions(ref e) => e,
+            Error::TzString(ref e)    => e,
+            Error::Io(ref e)          => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.inner(), f)
+    }
+}
+
+impl ErrorTrait for Error {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        self.inner().source()
+    }
+}
+
+impl From<files::Error> for Error {
+    fn from(e: files::Error) -> Error {
+        Error::Files(e)
+    }
+}
+
+impl From<line::Error> for Error {
+    fn from(e: line::Error) -> Error {
+        Error::Line(e)
+    }
+}
+
+impl From<table::Error> for Error {
+    fn from(e: table::Error) -> Error {
+        Error::Table(e)
+    }
+}
+
+impl From<leap::Error> for Error {
+    fn from(e: leap::Error) -> Error {
+        Error::Leap(e)
+    }
+}
+
+impl From<metadata::Error> for Error {
+    fn from(e: metadata::Error) -> Error {
+        Error::Metadata(e)
+    }
+}
+
+impl From<transitions::Error> for Error {
+    fn from(e: transitions::Error) -> Error {
+        Error::Transitions(e)
+    }
+}
+
+impl From<posix::Error> for Error {
+    fn from(e: posix::Error) -> Error {
+        Error::TzString(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use files::FileParser;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn thread_safe() {
+        assert_send_sync::<Error>();
+        assert_send_sync::<files::Error>();
+    }
+
+    fn build(contents: &str) -> Result<table::Table, Box<dyn ErrorTrait + Send + Sync>> {
+        let mut parser = FileParser::new();
+        parser.add_str("europe", contents).map_err(Error::from)?;
+        Ok(parser.build()?)
+    }
+
+    #[test]
+    fn boxed() {
+        let error = build("Zone\tEurope/London\t0:00\t-\tGMT\nZone\tEurope/London\t0:00\t-\tGMT\n").unwrap_err();
+        assert_eq!(error.to_string(), "europe:2: zone Europe/London is defined more than once");
+
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "zone Europe/London is defined more than once");
+    }
+}
@@ -0,0 +1,817 @@
+//! Parsing entire zoneinfo files, rather than one line at a time.
+//!
+//! The `LineParser` only knows how to deal with a single line of input, and
+//! the `TableBuilder` only knows how to deal with a single parsed line. This
+//! module glues the two together: it splits a file into lines, keeps track
+//! of where each line came from, and feeds them into a builder. This way,
+//! when something goes wrong, the error can say *where* it went wrong:
+//!
+//! ```text
+//! europe:1234: could not parse saving "1:0x"
+//! ```
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use parse_zoneinfo::files::FileParser;
+//!
+//! let mut parser = FileParser::new();
+//! parser.add_file("tzdata/europe").unwrap();
+//! parser.add_file("tzdata/backward").unwrap();
+//! let table = parser.build().unwrap();
+//! ```
+//!
+//! Tools that need to show or rewrite a file, rather than just read it,
+//! can use `AnnotatedLines` to keep its comments and original text.
+//!
+//! To read a whole unpacked tzdata release, which is spread over several
+//! files, use a `TzdataLoader` instead (or just `Table::from_tzdata_dir`).
+//! With the `tarball` feature enabled, the loader can also read the files
+//! straight out of a release archive; see the `archive` module.
+
+use std::error::Error as ErrorTrait;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use leap::{self, LeapSecondTable, LeapSecondTableBuilder};
+use line::{self, AnnotatedLine, Line, LineParser, Strictness};
+use metadata;
+use table::{self, Table, TableBuilder};
+
+pub use location::Location;
+
+
+/// A parsed line, along with the location it was read from.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LocatedLine<'a> {
+
+    /// Where in the file the line was found.
+    pub location: Location,
+
+    /// The parsed contents of the line.
+    pub line: Line<'a>,
+}
+
+
+/// Iterator over the parsed lines of a file’s contents.
+pub struct Lines<'a> {
+    parser: &'a LineParser,
+    lines: RawLines<'a>,
+}
+
+impl<'a> Lines<'a> {
+
+    /// Creates an iterator that parses each line of `contents` in turn,
+    /// reporting its location as being in a file with the given name.
+    pub fn new(parser: &'a LineParser, file: &'a str, contents: &'a str) -> Lines<'a> {
+        Lines {
+            parser,
+            lines: RawLines::new(file, contents),
+        }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Result<LocatedLine<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (location, text) = self.lines.next()?;
+
+        Some(match self.parser.parse_str(text) {
+            Ok(line) => Ok(LocatedLine { location, line }),
+            Err(e)   => Err(Error::Line(location, e)),
+        })
+    }
+}
+
+
+/// An annotated line, along with the location it was read from.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LocatedAnnotatedLine<'a> {
+
+    /// Where in the file the line was found. This only covers the line
+    /// itself, not its leading comments.
+    pub location: Location,
+
+    /// The parsed contents of the line, with its comments and raw text.
+    pub line: AnnotatedLine<'a>,
+}
+
+
+/// Iterator over the annotated lines of a file’s contents, which keeps
+/// hold of every comment and the original text of every line.
+///
+/// Comment-only lines directly above another line are attached to it as
+/// its leading comments, rather than being returned by themselves. Those
+/// followed by a blank line, a line that can’t be parsed, or the end of
+/// the file are returned as `Line::Space` lines with a trailing comment.
+/// Either way, writing out each line’s leading comments and raw text, in
+/// order, gives back the original file.
+pub struct AnnotatedLines<'a> {
+    parser: &'a LineParser,
+    lines: RawLines<'a>,
+    comments: Vec<LocatedAnnotatedLine<'a>>,
+    queued: VecDeque<Result<LocatedAnnotatedLine<'a>, Error>>,
+}
+
+impl<'a> AnnotatedLines<'a> {
+
+    /// Creates an iterator that parses each line of `contents` in turn,
+    /// reporting its location as being in a file with the given name.
+    pub fn new(parser: &'a LineParser, file: &'a str, contents: &'a str) -> AnnotatedLines<'a> {
+        AnnotatedLines {
+            parser,
+            lines: RawLines::new(file, contents),
+            comments: Vec::new(),
+            queued: VecDeque::new(),
+        }
+    }
+}
+
+impl<'a> Iterator for AnnotatedLines<'a> {
+    type Item = Result<LocatedAnnotatedLine<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(queued) = self.queued.pop_front() {
+            return Some(queued);
+        }
+
+        for (location, text) in self.lines.by_ref() {
+            let result = match self.parser.parse_annotated(text) {
+                Ok(line) => match line {
+                    AnnotatedLine { line: Line::Space, trailing_comment: Some(_), .. } => {
+                        self.comments.push(LocatedAnnotatedLine { location, line });
+                        continue;
+                    },
+                    AnnotatedLine { line: Line::Space, .. } => Ok(LocatedAnnotatedLine { location, line }),
+                    mut line => {
+                        line.leading_comments = self.comments.drain(..).map(|c| c.line.raw).collect();
+                        return Some(Ok(LocatedAnnotatedLine { location, line }));
+                    },
+                },
+                Err(e) => Err(Error::Line(location, e)),
+            };
+
+            // A blank or broken line separates the comments above it from
+            // whatever comes next, so they get returned on their own.
+            self.queued.extend(self.comments.drain(..).map(Ok));
+            self.queued.push_back(result);
+            return self.queued.pop_front();
+        }
+
+        self.queued.extend(self.comments.drain(..).map(Ok));
+        self.queued.pop_front()
+    }
+}
+
+
+/// Iterator over the unparsed lines of a file’s contents, along with their
+/// locations.
+pub(crate) struct RawLines<'a> {
+    file: &'a str,
+    contents: &'a str,
+    position: usize,
+    number: usize,
+}
+
+impl<'a> RawLines<'a> {
+    pub(crate) fn new(file: &'a str, contents: &'a str) -> RawLines<'a> {
+        RawLines {
+            file,
+            contents,
+            position: 0,
+            number: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for RawLines<'a> {
+    type Item = (Location, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.contents.len() {
+            return None;
+        }
+
+        let rest = &self.contents[self.position ..];
+        let (text, consumed) = match rest.find('\n') {
+            Some(pos) => (&rest[.. pos], pos + 1),
+            None      => (rest, rest.len()),
+        };
+
+        // Files checked out on Windows may have carriage returns left over.
+        let text = text.strip_suffix('\r').unwrap_or(text);
+
+        let start = self.position;
+        self.position += consumed;
+        self.number += 1;
+
+        let location = Location {
+            file: self.file.to_owned(),
+            line: self.number,
+            span: start .. start + text.len(),
+        };
+
+        Some((location, text))
+    }
+}
+
+
+/// Parses whole files, feeding every line into a `TableBuilder`.
+///
+/// By default, parsing stops at the first line that has a problem. A
+/// *lenient* parser instead carries on past bad lines, collecting an error
+/// for each one, so that every problem in a file can be reported at once:
+///
+/// ```no_run
+/// use parse_zoneinfo::files::FileParser;
+///
+/// let mut parser = FileParser::lenient();
+/// parser.add_file("custom.zi").unwrap();
+///
+/// let (table, diagnostics) = parser.build_lenient();
+/// for error in &diagnostics {
+///     eprintln!("{}", error);
+/// }
+/// ```
+pub struct FileParser {
+    parser: LineParser,
+    builder: TableBuilder,
+    lenient: bool,
+    diagnostics: Vec<Error>,
+}
+
+impl Default for FileParser {
+    fn default() -> Self {
+        FileParser::new()
+    }
+}
+
+impl FileParser {
+
+    /// Creates a new parser with an empty table.
+    pub fn new() -> FileParser {
+        FileParser {
+            parser: LineParser::new(),
+            builder: TableBuilder::new(),
+            lenient: false,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Creates a new lenient parser with an empty table, which carries on
+    /// past lines that can’t be parsed or added to the table.
+    pub fn lenient() -> FileParser {
+        FileParser {
+            lenient: true,
+            .. FileParser::new()
+        }
+    }
+
+    /// Makes this parser check each line with the given strictness, rather
+    /// than leniently.
+    pub fn with_strictness(mut self, strictness: Strictness) -> FileParser {
+        self.parser = LineParser::with_strictness(strictness);
+        self
+    }
+
+    /// Parses every line of the given string, and adds them to the table.
+    /// The file name is only used to report the locations of errors.
+    ///
+    /// Returns an error for the first line that either can’t be parsed or
+    /// can’t be added to the table. A lenient parser instead skips over
+    /// these lines, keeping the errors for `build_lenient` to return.
+    pub fn add_str(&mut self, file: &str, contents: &str) -> Result<(), Error> {
+        for result in Lines::new(&self.parser, file, contents) {
+            let error = match result {
+                Ok(located) => match self.builder.add_line_at(located.line, &located.location) {
+                    Ok(())  => continue,
+                    Err(e)  => Error::Table(located.location, e),
+                },
+                Err(e) => e,
+            };
+
+            if !self.lenient {
+                return Err(error);
+            }
+
+            self.builder.end_zone();
+            self.diagnostics.push(error);
+        }
+
+        Ok(())
+    }
+
+    /// Reads everything from the given reader, then parses it as with
+    /// `add_str`.
+    pub fn add_reader<R: Read>(&mut self, file: &str, mut reader: R) -> Result<(), Error> {
+        let mut contents = String::new();
+        if let Err(e) = reader.read_to_string(&mut contents) {
+            return Err(Error::Io(file.to_owned(), e));
+        }
+
+        self.add_str(file, &contents)
+    }
+
+    /// Reads the file at the given path, then parses it as with `add_str`.
+    /// Errors are reported using the path as the file name.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        match File::open(path) {
+            Ok(file) => self.add_reader(&name, file),
+            Err(e)   => Err(Error::Io(name, e)),
+        }
+    }
+
+    /// Returns the table after all the files have been added.
+    ///
+    /// Returns an error if any zones refer to rulesets that were never
+    /// defined in any of the files.
+    pub fn build(self) -> Result<Table, Error> {
+        self.builder.build().map_err(Error::Build)
+    }
+
+    /// Returns the table built from every line that could be added, along
+    /// with an error for each problem found along the way. Zones that refer
+    /// to rulesets that were never defined get left out of the table, as do
+    /// zones that were cut short by a skipped line, and links that don’t
+    /// lead to a zone.
+    pub fn build_lenient(self) -> (Table, Vec<Error>) {
+        let mut diagnostics = self.diagnostics;
+        let (table, errors) = self.builder.build_lenient();
+        diagnostics.extend(errors.into_iter().map(Error::Build));
+        (table, diagnostics)
+    }
+}
+
+
+/// Builds a table from the single zoneinfo file at the given path.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Table, Error> {
+    let mut parser = FileParser::new();
+    parser.add_file(path)?;
+    parser.build()
+}
+
+/// Builds a table from a single zoneinfo file read from the given reader.
+/// The file name is only used to report the locations of errors.
+pub fn parse_reader<R: Read>(file: &str, reader: R) -> Result<Table, Error> {
+    let mut parser = FileParser::new();
+    parser.add_reader(file, reader)?;
+    parser.build()
+}
+
+
+/// Builds a leap second table from the leap seconds file at the given path.
+pub fn parse_leap_file<P: AsRef<Path>>(path: P) -> Result<LeapSecondTable, Error> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    match File::open(path) {
+        Ok(file) => parse_leap_reader(&name, file),
+        Err(e)   => Err(Error::Io(name, e)),
+    }
+}
+
+/// Builds a leap second table from a leap seconds file read from the given
+/// reader. The file name is only used to report the locations of errors.
+pub fn parse_leap_reader<R: Read>(file: &str, mut reader: R) -> Result<LeapSecondTable, Error> {
+    let mut contents = String::new();
+    if let Err(e) = reader.read_to_string(&mut contents) {
+        return Err(Error::Io(file.to_owned(), e));
+    }
+
+    let parser = LineParser::new();
+    let mut builder = LeapSecondTableBuilder::new();
+    for result in Lines::new(&parser, file, &contents) {
+        let located = result?;
+        if let Err(e) = builder.add_line(located.line) {
+            return Err(Error::Leap(located.location, e));
+        }
+    }
+
+    Ok(builder.build())
+}
+
+
+/// The files in a tzdata release that contain rule, zone and link lines,
+/// in the order that the release’s Makefile passes them to zic.
+pub const TZDATA_FILES: &[&str] = &[
+    "africa",
+    "antarctica",
+    "asia",
+    "australasia",
+    "europe",
+    "northamerica",
+    "southamerica",
+    "etcetera",
+    "factory",
+    "backward",
+];
+
+
+/// Loads a `Table` from the files of an unpacked tzdata release.
+///
+/// By default, every file in `TZDATA_FILES` is read. Other files in the
+/// release, such as `backzone`, can be added to the list with `include`,
+/// and unwanted files can be taken out of it with `exclude`:
+///
+/// ```no_run
+/// use parse_zoneinfo::files::TzdataLoader;
+///
+/// let table = TzdataLoader::new()
+///     .include("backzone")
+///     .exclude("factory")
+///     .load_dir("tzdata")
+///     .unwrap();
+/// ```
+///
+/// Because the files are read as a whole, a zone may refer to a ruleset
+/// that only gets defined in a later file. When `backzone` is included,
+/// its zones will share their names with links in `backward`; the zone
+/// takes precedence when the table is queried.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TzdataLoader {
+    files: Vec<String>,
+    strictness: Strictness,
+}
+
+impl Table {
+
+    /// Builds a table out of the standard set of files in the unpacked
+    /// tzdata release at the given path. Use a `TzdataLoader` to change
+    /// which files get read.
+    pub fn from_tzdata_dir<P: AsRef<Path>>(path: P) -> Result<Table, Error> {
+        TzdataLoader::new().load_dir(path)
+    }
+}
+
+impl Default for TzdataLoader {
+    fn default() -> Self {
+        TzdataLoader::new()
+    }
+}
+
+impl TzdataLoader {
+
+    /// Creates a loader that reads the standard set of files.
+    pub fn new() -> TzdataLoader {
+        TzdataLoader {
+            files: TZDATA_FILES.iter().map(|&f| f.to_owned()).collect(),
+            strictness: Strictness::default(),
+        }
+    }
+
+    /// Adds a file to the end of the list of files to read, unless it’s
+    /// already in the list.
+    pub fn include(mut self, file: &str) -> TzdataLoader {
+        if !self.files.iter().any(|f| f == file) {
+            self.files.push(file.to_owned());
+        }
+
+        self
+    }
+
+    /// Removes a file from the list of files to read.
+    pub fn exclude(mut self, file: &str) -> TzdataLoader {
+        self.files.retain(|f| f != file);
+        self
+    }
+
+    /// Makes this loader check each line of the files it reads with the
+    /// given strictness, rather than leniently.
+    pub fn strictness(mut self, strictness: Strictness) -> TzdataLoader {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Returns the names of the files that will be read, in order.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Reads every file in the list from the given directory, and builds
+    /// them into a single table.
+    pub fn load_dir<P: AsRef<Path>>(&self, dir: P) -> Result<Table, Error> {
+        let sources = self.read_dir(dir.as_ref())?;
+        self.build_sources(&sources)
+    }
+
+    /// Reads every file in the list from the given directory, and builds
+    /// them into a single table as best it can, using a lenient
+    /// `FileParser`. Returns the table along with every problem found in
+    /// the files, or an error if any of the files couldn’t be read.
+    pub fn load_dir_lenient<P: AsRef<Path>>(&self, dir: P) -> Result<(Table, Vec<Error>), Error> {
+        let sources = self.read_dir(dir.as_ref())?;
+
+        let mut parser = FileParser::lenient().with_strictness(self.strictness);
+        for (name, contents) in &sources {
+            parser.add_str(name, contents)?;
+        }
+
+        Ok(parser.build_lenient())
+    }
+
+    /// Reads the contents of every file in the list from the given
+    /// directory, paired with their paths.
+    fn read_dir(&self, dir: &Path) -> Result<Vec<(String, String)>, Error> {
+        let mut sources = Vec::new();
+
+        for file in &self.files {
+            let path = dir.join(file);
+            let name = path.display().to_string();
+            let mut contents = String::new();
+
+            let result = File::open(&path).and_then(|mut f| f.read_to_string(&mut contents));
+            if let Err(e) = result {
+                return Err(Error::Io(name, e));
+            }
+
+            sources.push((name, contents));
+        }
+
+        Ok(sources)
+    }
+
+    /// Builds a table out of the given file names and contents.
+    pub(crate) fn build_sources(&self, sources: &[(String, String)]) -> Result<Table, Error> {
+        let mut parser = FileParser::new().with_strictness(self.strictness);
+        for (name, contents) in sources {
+            parser.add_str(name, contents)?;
+        }
+
+        parser.build()
+    }
+}
+
+
+/// Something that can go wrong while parsing a file into a `Table`.
+#[derive(Debug)]
+pub enum Error {
+
+    /// The file with the given name could not be read.
+    Io(String, io::Error),
+
+    /// The line at the given location could not be parsed.
+    Line(Location, line::Error),
+
+    /// The line at the given location was parsed, but could not be added to
+    /// the table.
+    Table(Location, table::Error),
+
+    /// The line at the given location was parsed, but could not be added to
+    /// the leap second table.
+    Leap(Location, leap::Error),
+
+    /// The line at the given location in a metadata file could not be
+    /// parsed.
+    Metadata(Location, metadata::Error),
+
+    /// Every line was added, but the table could not be built from them.
+    Build(table::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref file, ref e)            => write!(f, "{}: {}", file, e),
+            Error::Line(ref location, ref e)      => write!(f, "{}: {}", location, e),
+            Error::Table(ref location, ref e)     => write!(f, "{}: {}", location, e),
+            Error::Leap(ref location, ref e)      => write!(f, "{}: {}", location, e),
+            Error::Metadata(ref location, ref e)  => write!(f, "{}: {}", location, e),
+            Error::Build(ref e)                   => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn source(&self) -> Option<&(dyn ErrorTrait + 'static)> {
+        match *self {
+            Error::Io(_, ref e)        => Some(e),
+            Error::Line(_, ref e)      => Some(e),
+            Error::Table(_, ref e)     => Some(e),
+            Error::Leap(_, ref e)      => Some(e),
+            Error::Metadata(_, ref e)  => Some(e),
+            Error::Build(ref e)        => Some(e),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static EUROPE: &str = "\
+# Zone\tNAME\t\tSTDOFF\tRULES\tFORMAT\t[UNTIL]
+Zone\tEurope/London\t-0:01:15 -\tLMT\t1847 Dec  1
+\t\t\t 0:00\t-\tGMT\t1968 Oct 27
+\t\t\t 1:00\t1:0x\tBST\t1971 Oct 31  2:00u
+";
+
+    #[test]
+    fn locations() {
+        let parser = LineParser::new();
+        let lines = Lines::new(&parser, "europe", "\n\r\nLink  A  B\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+
+        let link = lines[2].as_ref().unwrap();
+        assert_eq!(link.location, Location { file: "europe".to_owned(), line: 3, span: 3 .. 13 });
+        assert_eq!(link.line, Line::Link(line::Link { existing: "A", new: "B" }));
+    }
+
+    #[test]
+    fn annotated_lines() {
+        static CONTENTS: &str = "\
+# Mauritius
+
+# From Steffen Thorsen (2008-06-25):
+# Mauritius plans to observe summer time.
+Zone Indian/Mauritius\t3:50:00 -\tLMT\t1907 # Port Louis
+\t\t\t4:00 Mauritius\t+04/+05
+# the end
+";
+
+        let parser = LineParser::new();
+        let lines = AnnotatedLines::new(&parser, "africa", CONTENTS).map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+
+        assert_eq!(lines[0].line.line, Line::Space);
+        assert_eq!(lines[0].line.trailing_comment, Some("# Mauritius"));
+        assert_eq!(lines[1].line.raw, "");
+
+        let zone = &lines[2];
+        assert_eq!(zone.location.line, 5);
+        assert_eq!(zone.line.leading_comments, vec![ "# From Steffen Thorsen (2008-06-25):", "# Mauritius plans to observe summer time." ]);
+        assert_eq!(zone.line.trailing_comment, Some("# Port Louis"));
+
+        assert_eq!(lines[4].line.trailing_comment, Some("# the end"));
+
+        let mut rewritten = String::new();
+        for located in &lines {
+            for comment in &located.line.leading_comments {
+                rewritten.push_str(comment);
+                rewritten.push('\n');
+            }
+            rewritten.push_str(located.line.raw);
+            rewritten.push('\n');
+        }
+        assert_eq!(rewritten, CONTENTS);
+    }
+
+    #[test]
+    fn annotated_comments_before_error() {
+        let parser = LineParser::new();
+        let mut lines = AnnotatedLines::new(&parser, "europe", "# comment\nGOLB\n");
+
+        assert_eq!(lines.next().unwrap().unwrap().line.raw, "# comment");
+        assert!(lines.next().unwrap().is_err());
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn line_error() {
+        let error = parse_reader("europe", EUROPE.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "europe:4: could not parse saving \"1:0x\"");
+    }
+
+    #[test]
+    fn table_error() {
+        let error = parse_reader("europe", "\n\t\t\t0:00\t-\tGMT\n".as_bytes()).unwrap_err();
+        match error {
+            Error::Table(location, table::Error::SurpriseContinuationLine) => assert_eq!(location.line, 2),
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn unknown_rulesets() {
+        let contents = "Zone\tEurope/Paris\t0:09:21 -\tLMT\t1891\n\t\t\t0:00\tFrance\tWE%sT\n";
+        let mut parser = FileParser::new();
+        parser.add_str("europe", contents).unwrap();
+        parser.add_str("backzone", "Zone\tEurope/Monaco\t0:00\tFrance\tWE%sT\n").unwrap();
+
+        let error = parser.build().unwrap_err();
+        assert_eq!(error.to_string(), "\
+europe:2: zone Europe/Paris refers to unknown ruleset \"France\"
+backzone:1: zone Europe/Monaco refers to unknown ruleset \"France\"");
+    }
+
+    #[test]
+    fn lenient() {
+        let contents = "\
+Rule\tGB-Eire\t1916\tonly\t-\tMay\t21\t2:00s\t1:00\tBST
+Zone\tEurope/London\t-0:01:15 -\tLMT\t1847 Dec  1
+\t\t\t 0:00\tGB-Eire\t%s
+Zone\tEurope/Dublin\t-0:25:21 -\tLMT\t1880 Aug  2
+\t\t\t-0:25:21 -\tDMT\t1916 May 21 2:00s
+\t\t\t 1:00\tIrish\tIST
+Zone\tEurope/Paris\t0:09:21 -\tLMT\t1891 Max 16
+\t\t\t0:09:21 -\tPMT\t1911 Mar 11
+Link\tEurope/Dublin\tEire
+Link\tEurope/London\tGB
+Link\tEurope/London\tGB
+Zone\tEurope/Rome\t0:49:56 -\tLMT\t1866 Dec 12
+\t\t\t0:49:56 -\tRMT\t1893 Oct 31 23:x
+";
+        let mut parser = FileParser::lenient();
+        parser.add_str("europe", contents).unwrap();
+        let (table, diagnostics) = parser.build_lenient();
+
+        let messages = diagnostics.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 7);
+        assert!(messages[0].starts_with("europe:7: "));
+        assert!(messages[1].starts_with("europe:8: "));
+        assert!(messages[2].starts_with("europe:11: "));
+        assert!(messages[3].starts_with("europe:13: "));
+        assert_eq!(messages[4], "europe:6: zone Europe/Dublin refers to unknown ruleset \"Irish\"");
+        assert_eq!(messages[5], "zone Europe/Rome was cut short by a line that could not be added");
+        assert_eq!(messages[6], "link Eire points to Europe/Dublin, which does not exist");
+
+        assert_eq!(table.zonesets["Europe/London"].len(), 2);
+        assert!(!table.zonesets.contains_key("Europe/Dublin"));
+        assert!(!table.zonesets.contains_key("Europe/Paris"));
+        assert!(!table.zonesets.contains_key("Europe/Rome"));
+        assert_eq!(table.links.len(), 1);
+    }
+
+    #[test]
+    fn loader_files() {
+        let loader = TzdataLoader::new().include("backzone").include("europe").exclude("factory");
+        assert_eq!(loader.files().len(), TZDATA_FILES.len());
+        assert_eq!(loader.files().last().unwrap(), "backzone");
+        assert!(!loader.files().iter().any(|f| f == "factory"));
+    }
+
+    #[test]
+    fn load_dir() {
+        use std::fs;
+
+        let dir = ::std::env::temp_dir().join(format!("parse-zoneinfo-load-dir-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in TZDATA_FILES {
+            fs::write(dir.join(file), "# nothing here\n").unwrap();
+        }
+
+        fs::write(dir.join("africa"), "Zone\tAfrica/Windhoek\t1:00\tNamibia\t%s\n").unwrap();
+        fs::write(dir.join("southamerica"), "Rule\tNamibia\t1994\tonly\t-\tMar\t21\t0:00\t-1:00\tWAT\n").unwrap();
+        fs::write(dir.join("backward"), "Link\tAfrica/Windhoek\tAfrica/Namibia\n").unwrap();
+
+        let table = Table::from_tzdata_dir(&dir).unwrap();
+        assert_eq!(table.zonesets["Africa/Windhoek"].len(), 1);
+        assert_eq!(table.rulesets["Namibia"].len(), 1);
+        assert_eq!(table.links["Africa/Namibia"], "Africa/Windhoek");
+
+        let error = TzdataLoader::new().include("backzone").load_dir(&dir).unwrap_err();
+        match error {
+            Error::Io(name, _) => assert!(name.ends_with("backzone")),
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        fs::write(dir.join("southamerica"), "Rule\tNamibia\t1994\tonly\t-\tMar\t0\t0:00\t-1:00\tWAT\n").unwrap();
+        assert!(TzdataLoader::new().load_dir(&dir).is_ok());
+
+        let error = TzdataLoader::new().strictness(Strictness::Strict).load_dir(&dir).unwrap_err();
+        match error {
+            Error::Line(location, line::Error::DayOutOfRange(_)) => assert!(location.file.ends_with("southamerica")),
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compact_format() {
+        let contents = "\
+# version 2024a
+# This zic input file is in the public domain.
+R d 1916 o - Jun 14 23s 1 S
+R d 1916 1919 - O Su>=1 23s 0 -
+Z Africa/Algiers 0:12:12 - LMT 1891 Mar 16
+0:9:21 - PMT 1911 Mar 11
+0 d WE%sT 1940 F 25 2
+1 - CET
+L Africa/Algiers Africa/Alger
+";
+        let table = parse_reader("tzdata.zi", contents.as_bytes()).unwrap();
+        assert_eq!(table.rulesets["d"].len(), 2);
+        assert_eq!(table.zonesets["Africa/Algiers"].len(), 4);
+        assert_eq!(table.links["Africa/Alger"], "Africa/Algiers");
+    }
+
+    #[test]
+    fn leap_file() {
+        let contents = "Leap\t2016\tDec\t31\t23:59:60\t+\tS\nExpires\t2016\tJun\t28\t00:00:00\n";
+        let error = parse_leap_reader("leapseconds", contents.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "leapseconds:2: expiry time 1467072000 does not come after the last leap second");
+    }
+
+    #[test]
+    fn whole_file() {
+        let contents = EUROPE.replace("1:0x", "1:00");
+        let table = parse_reader("europe", contents.as_bytes()).unwrap();
+        assert_eq!(table.zonesets["Europe/London"].len(), 3);
+    }
+}
@@ -0,0 +1,259 @@
+//! Leap second data, as found in the `leapseconds` file.
+//!
+//! The leap seconds file lives alongside the other zoneinfo files, but
+//! holds a different kind of data: rather than rules, zones, and links, it
+//! lists every leap second that has been inserted into (or, in theory,
+//! removed from) UTC, along with the time after which the list can no
+//! longer be trusted to be complete.
+//!
+//! ```tz
+//! Leap    2016    Dec     31      23:59:60        +       S
+//! Expires 2024    Dec     28      00:00:00
+//! ```
+//!
+//! These lines get built into a `LeapSecondTable`, which is kept separate
+//! from the main `Table`. zic only reads this file when it’s asked to
+//! generate the “right/” zones, whose clocks count leap seconds.
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use line::{self, Line, LeapType, ChangeTime, DaySpec, Month, TimeSpec, TimeType, Year};
+
+
+/// A **table** of all the leap seconds in a leap seconds file.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct LeapSecondTable {
+
+    /// Every leap second in the file, in order.
+    pub leap_seconds: Vec<LeapSecond>,
+
+    /// The time after which the leap second data is out of date, as a Unix
+    /// timestamp, or `None` if the file didn’t say.
+    pub expires: Option<i64>,
+}
+
+impl LeapSecondTable {
+
+    /// Returns whether the leap second data has gone stale by the given
+    /// Unix timestamp. Data without an expiry time never goes stale.
+    pub fn is_expired(&self, timestamp: i64) -> bool {
+        match self.expires {
+            Some(expires) => timestamp >= expires,
+            None          => false,
+        }
+    }
+}
+
+
+/// An individual leap second.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct LeapSecond {
+
+    /// The time written in the file, as a Unix timestamp. As Unix time
+    /// doesn’t count leap seconds, for an inserted leap second at 23:59:60
+    /// this is the same instant as midnight the following day.
+    pub timestamp: i64,
+
+    /// The number of seconds this leap second adds to UTC: `1` for an
+    /// inserted second, or `-1` for a removed one.
+    pub correction: i64,
+
+    /// Whether the time is given in UTC, or in each zone’s local time.
+    pub leap_type: LeapType,
+}
+
+impl LeapSecond {
+
+    /// Whether the time is in each zone’s local time, rather than UTC.
+    pub fn is_rolling(&self) -> bool {
+        self.leap_type == LeapType::Rolling
+    }
+}
+
+fn timestamp(year: i64, month: Month, day: i8, time: TimeSpec) -> i64 {
+    let time = time.with_type(TimeType::UTC);
+    ChangeTime::UntilTime(Year::Number(year), month, DaySpec::Ordinal(day), time).to_timestamp()
+}
+
+
+/// A builder for `LeapSecondTable` values based on leap and expires lines.
+#[derive(PartialEq, Debug, Default)]
+pub struct LeapSecondTableBuilder {
+
+    /// The table that’s being built up.
+    table: LeapSecondTable,
+}
+
+impl LeapSecondTableBuilder {
+
+    /// Creates a new builder with an empty table.
+    pub fn new() -> LeapSecondTableBuilder {
+        LeapSecondTableBuilder::default()
+    }
+
+    /// Adds a new line describing a leap second.
+    ///
+    /// Returns an error if the leap second doesn’t come after all the
+    /// previous ones, or doesn’t come before the expiry time.
+    pub fn add_leap_line(&mut self, leap_line: line::Leap) -> Result<(), Error> {
+        let leap_second = LeapSecond {
+            timestamp:  timestamp(leap_line.year, leap_line.month, leap_line.day, leap_line.time),
+            correction: leap_line.correction,
+            leap_type:  leap_line.leap_type,
+        };
+
+        if let Some(previous) = self.table.leap_seconds.last() {
+            if leap_second.timestamp <= previous.timestamp {
+                return Err(Error::OutOfOrder(leap_second.timestamp));
+            }
+        }
+
+        if let Some(expires) = self.table.expires {
+            if leap_second.timestamp >= expires {
+                return Err(Error::ExpiresBeforeLeap(expires));
+            }
+        }
+
+        self.table.leap_seconds.push(leap_second);
+        Ok(())
+    }
+
+    /// Adds a new line giving the time that the data expires.
+    ///
+    /// Returns an error if there was already an expires line, or the time
+    /// doesn’t come after every leap second.
+    pub fn add_expires_line(&mut self, expires_line: line::Expires) -> Result<(), Error> {
+        if self.table.expires.is_some() {
+            return Err(Error::DuplicateExpires);
+        }
+
+        let expires = timestamp(expires_line.year, expires_line.month, expires_line.day, expires_line.time);
+        if let Some(last) = self.table.leap_seconds.last() {
+            if last.timestamp >= expires {
+                return Err(Error::ExpiresBeforeLeap(expires));
+            }
+        }
+
+        self.table.expires = Some(expires);
+        Ok(())
+    }
+
+    /// Adds a parsed line of any type. Blank lines and comments are
+    /// accepted and ignored, but any lines that belong in the other
+    /// zoneinfo files result in an error.
+    pub fn add_line(&mut self, line: Line) -> Result<(), Error> {
+        match line {
+            Line::Space            => Ok(()),
+            Line::Leap(leap)       => self.add_leap_line(leap),
+            Line::Expires(expires) => self.add_expires_line(expires),
+            _                      => Err(Error::SurpriseDataLine),
+        }
+    }
+
+    /// Returns the table after it’s finished being built.
+    pub fn build(self) -> LeapSecondTable {
+        self.table
+    }
+}
+
+
+/// Something that can go wrong while constructing a `LeapSecondTable`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+
+    /// A rule, zone, or link line was passed in.
+    SurpriseDataLine,
+
+    /// A leap second was passed in that doesn’t come after the previous
+    /// one. This holds its timestamp.
+    OutOfOrder(i64),
+
+    /// An expires line was passed in, but there’s already been one.
+    DuplicateExpires,
+
+    /// The expiry time, which this holds, doesn’t come after every leap
+    /// second.
+    ExpiresBeforeLeap(i64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::SurpriseDataLine       => write!(f, "non-leap line in leap seconds file"),
+            Error::OutOfOrder(t)          => write!(f, "leap second at {} is out of order", t),
+            Error::DuplicateExpires       => write!(f, "multiple expires lines"),
+            Error::ExpiresBeforeLeap(t)   => write!(f, "expiry time {} does not come after the last leap second", t),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use line::LineParser;
+
+    fn build(lines: &[&str]) -> Result<LeapSecondTable, Error> {
+        let parser = LineParser::new();
+        let mut builder = LeapSecondTableBuilder::new();
+        for line in lines {
+            builder.add_line(parser.parse_str(line).unwrap())?;
+        }
+
+        Ok(builder.build())
+    }
+
+    #[test]
+    fn leap_seconds() {
+        let table = build(&[
+            "Leap	1972	Jun	30	23:59:60	+	S",
+            "Leap	2016	Dec	31	23:59:60	+	S",
+            "Expires	2024	Dec	28	00:00:00",
+        ]).unwrap();
+
+        assert_eq!(table.leap_seconds, vec![
+            LeapSecond { timestamp:    78_796_800, correction: 1, leap_type: LeapType::Stationary },
+            LeapSecond { timestamp: 1_483_228_800, correction: 1, leap_type: LeapType::Stationary },
+        ]);
+        assert_eq!(table.expires, Some(1_735_344_000));
+        assert!(!table.is_expired(1_735_343_999));
+        assert!(table.is_expired(1_735_344_000));
+    }
+
+    #[test]
+    fn out_of_order() {
+        let result = build(&[
+            "Leap	2016	Dec	31	23:59:60	+	S",
+            "Leap	1972	Jun	30	23:59:60	+	S",
+        ]);
+        assert_eq!(result, Err(Error::OutOfOrder(78_796_800)));
+    }
+
+    #[test]
+    fn expires_too_early() {
+        let result = build(&[
+            "Expires	2016	Jan	1	00:00:00",
+            "Leap	2016	Dec	31	23:59:60	+	S",
+        ]);
+        assert_eq!(result, Err(Error::ExpiresBeforeLeap(1_451_606_400)));
+    }
+
+    #[test]
+    fn two_expires() {
+        let result = build(&[
+            "Expires	2024	Dec	28	00:00:00",
+            "Expires	2025	Jun	28	00:00:00",
+        ]);
+        assert_eq!(result, Err(Error::DuplicateExpires));
+    }
+
+    #[test]
+    fn data_line() {
+        let result = build(&["Link	Europe/London	GB"]);
+        assert_eq!(result, Err(Error::SurpriseDataLine));
+    }
+}
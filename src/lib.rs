@@ -1,6 +1,22 @@
 extern crate regex;
+#[cfg(feature = "tarball")]
+extern crate flate2;
+#[cfg(feature = "tarball")]
+extern crate tar;
 
+#[cfg(feature = "tarball")]
+pub mod archive;
+mod error;
+pub mod files;
+pub mod leap;
 pub mod line;
+pub mod location;
+pub mod metadata;
+pub mod posix;
 pub mod table;
 pub mod transitions;
 pub mod structure;
+pub mod tzif;
+pub mod validate;
+
+pub use error::Error;
@@ -1,3 +1,6 @@
+use std::cmp::Ordering;
+use std::error::Error as ErrorTrait;
+use std::fmt;
 use std::str::FromStr;
 // we still support rust that doesn't have the inherent methods
 #[allow(deprecated, unused_imports)]
@@ -6,37 +9,154 @@ use std::ascii::AsciiExt;
 use regex::{Regex, Captures};
 
 pub struct LineParser {
+    strictness: Strictness,
     rule_line: Regex,
     day_field: Regex,
-    hm_field: Regex,
-    hms_field: Regex,
+    time_field: Regex,
     zone_line: Regex,
     continuation_line: Regex,
     link_line: Regex,
+    leap_line: Regex,
+    expires_line: Regex,
     empty_line: Regex,
 }
 
+/// How closely a `LineParser` sticks to the grammar described in zic.8.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub enum Strictness {
+
+    /// Accept what the parser has always accepted: zone names are limited
+    /// to ASCII letters, digits, and `/_+-`, and numeric fields aren’t
+    /// range-checked beyond what’s needed to store and use them.
+    #[default]
+    Lenient,
+
+    /// Accept exactly what zic accepts, rejecting zone and link names, day
+    /// numbers, and abbreviation formats that zic would refuse.
+    Strict,
+}
+
+/// Something that can go wrong while parsing a line. Each variant holds
+/// the text that couldn’t be parsed, where there is any.
 #[derive(PartialEq, Debug, Clone)]
 pub enum Error {
+
+    /// A year field wasn’t a number, `minimum`, or `maximum`.
     FailedYearParse(String),
+
+    /// A month field wasn’t the name of a month.
     FailedMonthParse(String),
+
+    /// A weekday field wasn’t the name of a weekday.
     FailedWeekdayParse(String),
+
+    /// The line didn’t look like any of the known types of line.
     InvalidLineType(String),
+
+    /// The obsolete type column of a rule line held something other than
+    /// a hyphen.
     TypeColumnContainedNonHyphen(String),
+
+    /// The saving column of a zone line couldn’t be parsed.
     CouldNotParseSaving(String),
+
+    /// A day field wasn’t a number, or a `last` or `>=`/`<=` weekday.
     InvalidDaySpec(String),
+
+    /// A time field couldn’t be parsed, or had an unknown suffix.
     InvalidTimeSpecAndType(String),
+
+    /// A time field had a suffix where only wall clock time is allowed.
     NonWallClockInTimeSpec(String),
+
+    /// A time field was too far from zero to be stored, or an offset or
+    /// saving was a week or more.
+    TimeOutOfRange(String),
+
+    /// The correction column of a leap line wasn’t `+` or `-`.
+    InvalidLeapCorrection(String),
+
+    /// The type column of a leap line wasn’t `R` or `S`.
+    InvalidLeapType(String),
+
+    /// A zone or link name had an empty, `.` or `..` component. Only
+    /// checked by strict parsers.
+    InvalidZoneName(String),
+
+    /// A day of the month was zero, or past the end of its month. Only
+    /// checked by strict parsers.
+    DayOutOfRange(String),
+
+    /// A format column had a `%` that wasn’t `%s` or `%z`, more than one
+    /// `%`, or a `%` alongside a `/`. Only checked by strict parsers.
+    InvalidFormat(String),
+
+    /// The line wasn’t a rule line.
     NotParsedAsRuleLine,
+
+    /// The line wasn’t a zone line.
     NotParsedAsZoneLine,
+
+    /// The line wasn’t a link line.
     NotParsedAsLinkLine,
+
+    /// The line wasn’t a leap line.
+    NotParsedAsLeapLine,
+
+    /// The line wasn’t an expires line.
+    NotParsedAsExpiresLine,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::FailedYearParse(ref s)              => write!(f, "failed to parse year \"{}\"", s),
+            Error::FailedMonthParse(ref s)             => write!(f, "failed to parse month \"{}\"", s),
+            Error::FailedWeekdayParse(ref s)           => write!(f, "failed to parse weekday \"{}\"", s),
+            Error::InvalidLineType(ref s)              => write!(f, "invalid line type \"{}\"", s),
+            Error::TypeColumnContainedNonHyphen(ref s) => write!(f, "type column contained \"{}\" instead of a hyphen", s),
+            Error::CouldNotParseSaving(ref s)          => write!(f, "could not parse saving \"{}\"", s),
+            Error::InvalidDaySpec(ref s)               => write!(f, "invalid day specification \"{}\"", s),
+            Error::InvalidTimeSpecAndType(ref s)       => write!(f, "invalid time \"{}\"", s),
+            Error::NonWallClockInTimeSpec(ref s)       => write!(f, "time \"{}\" must not have a time type suffix", s),
+            Error::TimeOutOfRange(ref s)               => write!(f, "time \"{}\" is out of range", s),
+            Error::InvalidLeapCorrection(ref s)        => write!(f, "invalid leap second correction \"{}\"", s),
+            Error::InvalidLeapType(ref s)              => write!(f, "invalid leap second type \"{}\"", s),
+            Error::InvalidZoneName(ref s)              => write!(f, "invalid zone name \"{}\"", s),
+            Error::DayOutOfRange(ref s)                => write!(f, "day \"{}\" is out of range", s),
+            Error::InvalidFormat(ref s)                => write!(f, "invalid abbreviation format \"{}\"", s),
+            Error::NotParsedAsRuleLine                 => write!(f, "not parsed as a rule line"),
+            Error::NotParsedAsZoneLine                 => write!(f, "not parsed as a zone line"),
+            Error::NotParsedAsLinkLine                 => write!(f, "not parsed as a link line"),
+            Error::NotParsedAsLeapLine                 => write!(f, "not parsed as a leap line"),
+            Error::NotParsedAsExpiresLine              => write!(f, "not parsed as an expires line"),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+}
+
+impl Default for LineParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LineParser {
+
+    /// Creates a new lenient parser.
     pub fn new() -> Self {
+        LineParser::with_strictness(Strictness::default())
+    }
+
+    /// Creates a new parser that checks lines as closely as the given
+    /// strictness says to.
+    pub fn with_strictness(strictness: Strictness) -> Self {
         LineParser {
+            strictness,
             rule_line: Regex::new(r##"(?x) ^
-                Rule \s+
+                ( ?P<keyword> [ A-Z a-z ]+ )  \s+
                 ( ?P<name>    \S+)  \s+
                 ( ?P<from>    \S+)  \s+
                 ( ?P<to>      \S+)  \s+
@@ -46,7 +166,7 @@ impl LineParser {
                 ( ?P<at>      \S+)  \s+
                 ( ?P<save>    \S+)  \s+
                 ( ?P<letters> \S+)  \s*
-                (\#.*)?
+                ( ?P<comment> \#.* )?
             $ "##).unwrap(),
 
             day_field: Regex::new(r##"(?x) ^
@@ -55,77 +175,163 @@ impl LineParser {
                 ( ?P<day>     \d+ )
             $ "##).unwrap(),
 
-            hm_field: Regex::new(r##"(?x) ^
+            time_field: Regex::new(r##"(?x) ^
                 ( ?P<sign> -? )
-                ( ?P<hour> \d{1,2} ) : ( ?P<minute> \d{2} )
-                ( ?P<flag> [wsugz] )?
-            $ "##).unwrap(),
-
-            hms_field: Regex::new(r##"(?x) ^
-                ( ?P<sign> -? )
-                ( ?P<hour> \d{1,2} ) : ( ?P<minute> \d{2} ) : ( ?P<second> \d{2} )
+                ( ?P<hour> \d+ )
+                ( : ( ?P<minute> \d+ )
+                    ( : ( ?P<second> \d+ )
+                        ( \. ( ?P<fraction> \d+ ) )? )? )?
                 ( ?P<flag> [wsugz] )?
             $ "##).unwrap(),
 
             zone_line: Regex::new(r##"(?x) ^
-                Zone \s+
-                ( ?P<name> [ A-Z a-z 0-9 / _ + - ]+ )  \s+
+                ( ?P<keyword> [ A-Z a-z ]+ )  \s+
+                ( ?P<name>       \S+ )  \s+
                 ( ?P<gmtoff>     \S+ )  \s+
                 ( ?P<rulessave>  \S+ )  \s+
                 ( ?P<format>     \S+ )  \s*
-                ( ?P<year>       \S+ )? \s*
-                ( ?P<month>      \S+ )? \s*
-                ( ?P<day>        \S+ )? \s*
-                ( ?P<time>       \S+ )? \s*
-                (\#.*)?
+                ( ?P<year>       [^\s\#]\S* )? \s*
+                ( ?P<month>      [^\s\#]\S* )? \s*
+                ( ?P<day>        [^\s\#]\S* )? \s*
+                ( ?P<time>       [^\s\#]\S* )? \s*
+                ( ?P<comment> \#.* )?
             $ "##).unwrap(),
 
             continuation_line: Regex::new(r##"(?x) ^
-                \s+
+                \s*
                 ( ?P<gmtoff>     \S+ )  \s+
                 ( ?P<rulessave>  \S+ )  \s+
                 ( ?P<format>     \S+ )  \s*
-                ( ?P<year>       \S+ )? \s*
-                ( ?P<month>      \S+ )? \s*
-                ( ?P<day>        \S+ )? \s*
-                ( ?P<time>       \S+ )? \s*
-                (\#.*)?
+                ( ?P<year>       [^\s\#]\S* )? \s*
+                ( ?P<month>      [^\s\#]\S* )? \s*
+                ( ?P<day>        [^\s\#]\S* )? \s*
+                ( ?P<time>       [^\s\#]\S* )? \s*
+                ( ?P<comment> \#.* )?
             $ "##).unwrap(),
 
             link_line: Regex::new(r##"(?x) ^
-                Link  \s+
+                ( ?P<keyword> [ A-Z a-z ]+ )  \s+
                 ( ?P<target>  \S+ )  \s+
                 ( ?P<name>    \S+ )  \s*
-                (\#.*)?
+                ( ?P<comment> \#.* )?
+            $ "##).unwrap(),
+
+            leap_line: Regex::new(r##"(?x) ^
+                ( ?P<keyword>     [ A-Z a-z ]+ )  \s+
+                ( ?P<year>        \S+ )  \s+
+                ( ?P<month>       \S+ )  \s+
+                ( ?P<day>         \S+ )  \s+
+                ( ?P<time>        \S+ )  \s+
+                ( ?P<correction>  \S+ )  \s+
+                ( ?P<type>        \S+ )  \s*
+                ( ?P<comment> \#.* )?
+            $ "##).unwrap(),
+
+            expires_line: Regex::new(r##"(?x) ^
+                ( ?P<keyword>  [ A-Z a-z ]+ )  \s+
+                ( ?P<year>     \S+ )  \s+
+                ( ?P<month>    \S+ )  \s+
+                ( ?P<day>      \S+ )  \s+
+                ( ?P<time>     \S+ )  \s*
+                ( ?P<comment> \#.* )?
             $ "##).unwrap(),
 
             empty_line: Regex::new(r##"(?x) ^
                 \s*
-                (\#.*)?
+                ( ?P<comment> \#.* )?
             $"##).unwrap(),
         }
     }
 }
 
+/// Looks up a word in a table of names, the way zic does: case doesn’t
+/// matter, and a word can be abbreviated to any prefix of a name, as long
+/// as that prefix doesn’t belong to any other name in the table. An exact
+/// match always wins, even if it’s also a prefix of another name.
+fn lookup<T: Copy>(input: &str, table: &[(&str, T)]) -> Option<T> {
+    let input = input.to_ascii_lowercase();
+
+    if let Some(&(_, value)) = table.iter().find(|&&(name, _)| name == input) {
+        return Some(value);
+    }
+
+    let mut matches = table.iter().filter(|&&(name, _)| name.starts_with(&*input));
+    match (matches.next(), matches.next()) {
+        (Some(&(_, value)), None) if !input.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+/// The keywords that begin each type of line.
 #[derive(PartialEq, Debug, Copy, Clone)]
+enum Keyword {
+    Rule,
+    Zone,
+    Link,
+    Leap,
+    Expires,
+}
+
+const KEYWORDS: &[(&str, Keyword)] = &[
+    ("rule", Keyword::Rule),
+    ("zone", Keyword::Zone),
+    ("link", Keyword::Link),
+];
+
+/// The keywords that only appear in the leap seconds file. zic looks these
+/// up separately from the others, which is why “L” means “Link” rather
+/// than being ambiguous.
+const LEAP_KEYWORDS: &[(&str, Keyword)] = &[
+    ("leap",    Keyword::Leap),
+    ("expires", Keyword::Expires),
+];
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum Year {
     Minimum,
     Maximum,
     Number(i64),
 }
 
+/// Years are ordered with `minimum` before every numbered year, and
+/// `maximum` after every one.
+impl Ord for Year {
+    fn cmp(&self, other: &Year) -> Ordering {
+        fn key(year: Year) -> (i8, i64) {
+            match year {
+                Year::Minimum      => (-1, 0),
+                Year::Number(year) => (0, year),
+                Year::Maximum      => (1, 0),
+            }
+        }
+
+        key(*self).cmp(&key(*other))
+    }
+}
+
+impl PartialOrd for Year {
+    fn partial_cmp(&self, other: &Year) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+const YEAR_NAMES: &[(&str, Year)] = &[
+    ("minimum", Year::Minimum),
+    ("maximum", Year::Maximum),
+];
+
 impl FromStr for Year {
     type Err = Error;
 
     fn from_str(input: &str) -> Result<Year, Self::Err> {
-        Ok(match &*input.to_ascii_lowercase() {
-            "min" | "minimum" => Year::Minimum,
-            "max" | "maximum" => Year::Maximum,
-            year => match year.parse() {
-                Ok(year) => Year::Number(year),
-                Err(_)   => return Err(Error::FailedYearParse(input.to_string())),
-            }
-        })
+        if let Some(year) = lookup(input, YEAR_NAMES) {
+            return Ok(year);
+        }
+
+        match input.parse() {
+            Ok(year) => Ok(Year::Number(year)),
+            Err(_)   => Err(Error::FailedYearParse(input.to_string())),
+        }
     }
 }
 
@@ -146,7 +352,7 @@ pub enum Month {
 }
 
 impl Month {
-    fn length(self, is_leap: bool) -> i8 {
+    pub(crate) fn length(self, is_leap: bool) -> i8 {
         match self {
             Month::January             => 31,
             Month::February if is_leap => 29,
@@ -163,27 +369,40 @@ impl Month {
             Month::December            => 31,
         }
     }
+
+    /// Returns the number of days in the year before this month starts.
+    pub(crate) fn days_before(self, is_leap: bool) -> i64 {
+        MONTH_NAMES.iter()
+                   .map(|&(_, month)| month)
+                   .take_while(|&month| month != self)
+                   .map(|month| i64::from(month.length(is_leap)))
+                   .sum()
+    }
 }
 
+const MONTH_NAMES: &[(&str, Month)] = &[
+    ("january",    Month::January),
+    ("february",   Month::February),
+    ("march",      Month::March),
+    ("april",      Month::April),
+    ("may",        Month::May),
+    ("june",       Month::June),
+    ("july",       Month::July),
+    ("august",     Month::August),
+    ("september",  Month::September),
+    ("october",    Month::October),
+    ("november",   Month::November),
+    ("december",   Month::December),
+];
+
 impl FromStr for Month {
     type Err = Error;
 
     fn from_str(input: &str) -> Result<Month, Self::Err> {
-        Ok(match &*input.to_ascii_lowercase() {
-            "jan" | "january"    => Month::January,
-            "feb" | "february"   => Month::February,
-            "mar" | "march"      => Month::March,
-            "apr" | "april"      => Month::April,
-            "may"                => Month::May,
-            "jun" | "june"       => Month::June,
-            "jul" | "july"       => Month::July,
-            "aug" | "august"     => Month::August,
-            "sep" | "september"  => Month::September,
-            "oct" | "october"    => Month::October,
-            "nov" | "november"   => Month::November,
-            "dec" | "december"   => Month::December,
-            other                => return Err(Error::FailedMonthParse(other.to_string())),
-        })
+        match lookup(input, MONTH_NAMES) {
+            Some(month) => Ok(month),
+            None        => Err(Error::FailedMonthParse(input.to_ascii_lowercase())),
+        }
     }
 }
 
@@ -198,20 +417,24 @@ pub enum Weekday {
     Saturday,
 }
 
+const WEEKDAY_NAMES: &[(&str, Weekday)] = &[
+    ("sunday",     Weekday::Sunday),
+    ("monday",     Weekday::Monday),
+    ("tuesday",    Weekday::Tuesday),
+    ("wednesday",  Weekday::Wednesday),
+    ("thursday",   Weekday::Thursday),
+    ("friday",     Weekday::Friday),
+    ("saturday",   Weekday::Saturday),
+];
+
 impl FromStr for Weekday {
     type Err = Error;
 
     fn from_str(input: &str) -> Result<Weekday, Self::Err> {
-        Ok(match &*input.to_ascii_lowercase() {
-            "mon" | "monday"     => Weekday::Monday,
-            "tue" | "tuesday"    => Weekday::Tuesday,
-            "wed" | "wednesday"  => Weekday::Wednesday,
-            "thu" | "thursday"   => Weekday::Thursday,
-            "fri" | "friday"     => Weekday::Friday,
-            "sat" | "saturday"   => Weekday::Saturday,
-            "sun" | "sunday"     => Weekday::Sunday,
-            other                => return Err(Error::FailedWeekdayParse(other.to_string())),
-        })
+        match lookup(input, WEEKDAY_NAMES) {
+            Some(weekday) => Ok(weekday),
+            None          => Err(Error::FailedWeekdayParse(input.to_ascii_lowercase())),
+        }
     }
 }
 
@@ -229,7 +452,7 @@ impl Weekday {
         let y = if m < 3 { year - 1} else { year };
         let d = day as i64;
         const T: [i64; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
-        match (y + y/4 - y/100 + y/400 + T[m as usize-1] + d) % 7 {
+        match (y + y.div_euclid(4) - y.div_euclid(100) + y.div_euclid(400) + T[m as usize-1] + d).rem_euclid(7) {
             0 => Weekday::Sunday,
             1 => Weekday::Monday,
             2 => Weekday::Tuesday,
@@ -237,7 +460,7 @@ impl Weekday {
             4 => Weekday::Thursday,
             5 => Weekday::Friday,
             6 => Weekday::Saturday,
-            _ => panic!("why is negative modulus designed so?")
+            _ => unreachable!("rem_euclid(7) is always between 0 and 6")
         }
     }
 }
@@ -258,6 +481,9 @@ fn weekdays() {
     assert_eq!(Weekday::calculate(2016, Month::November, 18), Weekday::Friday);
     assert_eq!(Weekday::calculate(2010, Month::December, 19), Weekday::Sunday);
     assert_eq!(Weekday::calculate(2016, Month::February, 29), Weekday::Monday);
+    assert_eq!(Weekday::calculate(1, Month::January, 1), Weekday::Monday);
+    assert_eq!(Weekday::calculate(0, Month::January, 1), Weekday::Saturday);
+    assert_eq!(Weekday::calculate(-1, Month::January, 1), Weekday::Friday);
 }
 
 fn is_leap(year: i64) -> bool {
@@ -290,19 +516,83 @@ fn leap_years() {
 }
 
 impl DaySpec {
-    pub fn to_concrete_day(&self, year: i64, month: Month) -> i8 {
+
+    /// Returns the date this refers to in the given year and month, as a
+    /// year, month, and day. The weekday forms can land in the month before
+    /// or after, and zic allows this: `Sun>=29` in a February without a
+    /// Sunday on the 29th is a day in March, and `Sat<=1` can be a day in
+    /// the previous month. Ordinal days are returned as they are.
+    pub fn to_concrete_date(&self, year: i64, month: Month) -> (i64, Month, i8) {
         let length = month.length(is_leap(year));
 
         match *self {
-            DaySpec::Ordinal(day) => day,
-            DaySpec::Last(weekday) => (1..length+1).rev()
-                .find(|&day| Weekday::calculate(year, month, day) == weekday).unwrap(),
-            DaySpec::LastOnOrBefore(weekday, day) => (1..day+1).rev()
-                .find(|&day| Weekday::calculate(year, month, day) == weekday).unwrap(),
-            DaySpec::FirstOnOrAfter(weekday, day) => (day..length+1)
-                .find(|&day| Weekday::calculate(year, month, day) == weekday).unwrap(),
+            DaySpec::Ordinal(day) => (year, month, day),
+            DaySpec::Last(weekday) => {
+                let day = (1..length+1).rev()
+                    .find(|&day| Weekday::calculate(year, month, day) == weekday)
+                    .expect("Every month has every weekday");
+                (year, month, day)
+            },
+            DaySpec::LastOnOrBefore(weekday, day) => {
+                let mut date = normalise_date(year, month, i64::from(day.min(length)));
+                while Weekday::calculate(date.0, date.1, date.2) != weekday {
+                    date = normalise_date(date.0, date.1, i64::from(date.2) - 1);
+                }
+                date
+            },
+            DaySpec::FirstOnOrAfter(weekday, day) => {
+                let mut date = normalise_date(year, month, i64::from(day));
+                while Weekday::calculate(date.0, date.1, date.2) != weekday {
+                    date = normalise_date(date.0, date.1, i64::from(date.2) + 1);
+                }
+                date
+            },
         }
     }
+
+    /// Returns the date this refers to in the given year and month, like
+    /// `to_concrete_date`, or `None` if zic wouldn’t accept it: an ordinal
+    /// day that isn’t in the month that year, or a weekday form whose day
+    /// isn’t in the month in any year.
+    pub fn checked_concrete_date(&self, year: i64, month: Month) -> Option<(i64, Month, i8)> {
+        let valid = match *self {
+            DaySpec::Ordinal(day) => day >= 1 && day <= month.length(is_leap(year)),
+            DaySpec::Last(_) => true,
+            DaySpec::LastOnOrBefore(_, day) | DaySpec::FirstOnOrAfter(_, day) => day >= 1 && day <= month.length(true),
+        };
+
+        if valid { Some(self.to_concrete_date(year, month)) } else { None }
+    }
+
+    /// Returns the day of the month this refers to in the given year and
+    /// month.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the day falls in the month before or after; use
+    /// `to_concrete_date` to get those.
+    pub fn to_concrete_day(&self, year: i64, month: Month) -> i8 {
+        let (_, concrete_month, day) = self.to_concrete_date(year, month);
+        assert!(concrete_month == month, "Day falls outside of its month");
+        day
+    }
+}
+
+/// Moves a day that falls outside of its month into the month it actually
+/// falls in, however many months away that is, crossing into other years
+/// if necessary.
+fn normalise_date(mut year: i64, mut month: Month, mut day: i64) -> (i64, Month, i8) {
+    while day < 1 {
+        if month == Month::January { year -= 1; month = Month::December; } else { month = MONTH_NAMES[month as usize - 2].1; }
+        day += i64::from(month.length(is_leap(year)));
+    }
+
+    while day > i64::from(month.length(is_leap(year))) {
+        day -= i64::from(month.length(is_leap(year)));
+        if month == Month::December { year += 1; month = Month::January; } else { month = MONTH_NAMES[month as usize].1; }
+    }
+
+    (year, month, day as i8)
 }
 
 #[cfg(test)]
@@ -323,6 +613,29 @@ fn last_monday() {
     assert_eq!(dayspec.to_concrete_day(2016, Month::December), 26);
 }
 
+#[cfg(test)]
+#[test]
+fn concrete_dates() {
+    assert_eq!(DaySpec::FirstOnOrAfter(Weekday::Sunday, 29).to_concrete_date(2015, Month::February), (2015, Month::March, 1));
+    assert_eq!(DaySpec::FirstOnOrAfter(Weekday::Monday, 29).to_concrete_date(2016, Month::February), (2016, Month::February, 29));
+    assert_eq!(DaySpec::FirstOnOrAfter(Weekday::Sunday, 29).to_concrete_date(2016, Month::December), (2017, Month::January, 1));
+    assert_eq!(DaySpec::LastOnOrBefore(Weekday::Friday, 1).to_concrete_date(2016, Month::October), (2016, Month::September, 30));
+    assert_eq!(DaySpec::LastOnOrBefore(Weekday::Saturday, 1).to_concrete_date(2016, Month::January), (2015, Month::December, 26));
+    assert_eq!(DaySpec::LastOnOrBefore(Weekday::Sunday, 29).to_concrete_date(2015, Month::February), (2015, Month::February, 22));
+    assert_eq!(DaySpec::FirstOnOrAfter(Weekday::Sunday, 127).to_concrete_date(2016, Month::January), (2016, Month::May, 8));
+    assert_eq!(DaySpec::LastOnOrBefore(Weekday::Sunday, -40).to_concrete_date(2016, Month::March), (2016, Month::January, 17));
+}
+
+#[cfg(test)]
+#[test]
+fn checked_concrete_date() {
+    assert_eq!(DaySpec::FirstOnOrAfter(Weekday::Sunday, 29).checked_concrete_date(2015, Month::February), Some((2015, Month::March, 1)));
+    assert_eq!(DaySpec::FirstOnOrAfter(Weekday::Sunday, 30).checked_concrete_date(2016, Month::February), None);
+    assert_eq!(DaySpec::Ordinal(29).checked_concrete_date(2015, Month::February), None);
+    assert_eq!(DaySpec::Ordinal(29).checked_concrete_date(2016, Month::February), Some((2016, Month::February, 29)));
+    assert_eq!(DaySpec::Last(Weekday::Sunday).checked_concrete_date(2016, Month::March), Some((2016, Month::March, 27)));
+}
+
 #[cfg(test)]
 #[test]
 fn first_monday_on_or_after() {
@@ -341,22 +654,36 @@ fn first_monday_on_or_after() {
     assert_eq!(dayspec.to_concrete_day(2016, Month::December), 26);
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub enum TimeSpec {
-    Hours(i8),
-    HoursMinutes(i8, i8),
-    HoursMinutesSeconds(i8, i8, i8),
-    Zero,
-}
+/// A time of day, or a length of time such as an offset or a saving,
+/// stored as a number of seconds. It can be negative, and can go past 24
+/// hours: zic accepts times such as `25:00` or even `260:00`, although
+/// offsets and savings only go up to `167:59:59` either way.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct TimeSpec(i64);
 
 impl TimeSpec {
+
+    /// No time at all, written as `-` or `0`.
+    pub const ZERO: TimeSpec = TimeSpec(0);
+
+    pub fn from_seconds(seconds: i64) -> TimeSpec {
+        TimeSpec(seconds)
+    }
+
+    pub fn hours(hours: i64) -> TimeSpec {
+        TimeSpec(hours * 60 * 60)
+    }
+
+    pub fn hours_minutes(hours: i64, minutes: i64) -> TimeSpec {
+        TimeSpec(hours * 60 * 60 + minutes * 60)
+    }
+
+    pub fn hours_minutes_seconds(hours: i64, minutes: i64, seconds: i64) -> TimeSpec {
+        TimeSpec(hours * 60 * 60 + minutes * 60 + seconds)
+    }
+
     pub fn as_seconds(self) -> i64 {
-        match self {
-            TimeSpec::Hours(h) => h as i64 * 60 * 60,
-            TimeSpec::HoursMinutes(h, m) => h as i64 * 60 * 60 + m as i64 * 60,
-            TimeSpec::HoursMinutesSeconds(h, m, s) => h as i64 * 60 * 60 + m as i64 * 60 + s as i64,
-            TimeSpec::Zero => 0,
-        }
+        self.0
     }
 }
 
@@ -370,10 +697,28 @@ pub enum TimeType {
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct TimeSpecAndType(pub TimeSpec, pub TimeType);
 
+/// A SAVE amount, along with the `s` or `d` suffix saying whether the time
+/// it’s in effect counts as daylight-saving time, if it had one.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct TimeSpecAndDst(pub TimeSpec, pub Option<bool>);
+
+impl TimeSpecAndDst {
+
+    /// Whether the time this amount is in effect counts as daylight-saving
+    /// time. Without a suffix, this is whenever the amount isn’t zero.
+    pub fn is_dst(&self) -> bool {
+        self.1.unwrap_or_else(|| self.0.as_seconds() != 0)
+    }
+}
+
 impl TimeSpec {
     pub fn with_type(self, timetype: TimeType) -> TimeSpecAndType {
         TimeSpecAndType(self, timetype)
     }
+
+    pub fn with_dst(self, is_dst: bool) -> TimeSpecAndDst {
+        TimeSpecAndDst(self, Some(is_dst))
+    }
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
@@ -403,7 +748,7 @@ impl ChangeTime {
             }
         }
 
-        fn time_to_timestamp(year: i64, month: i8, day: i8, hour: i8, minute: i8, second: i8) -> i64 {
+        fn time_to_timestamp(year: i64, month: i8, day: i8, seconds: i64) -> i64 {
             const MONTHS_NON_LEAP: [i64; 12] = [
                 0,
                 31,
@@ -435,20 +780,19 @@ impl ChangeTime {
             seconds_until_start_of_year(year)
                 + 60 * 60 * 24 * if is_leap(year) { MONTHS_LEAP[month as usize - 1] } else { MONTHS_NON_LEAP[month as usize - 1] }
                 + 60 * 60 * 24 * (day as i64 - 1)
-                + 60 * 60 * hour as i64
-                + 60 * minute as i64
-                + second as i64
+                + seconds
         }
 
         match *self {
-            ChangeTime::UntilYear(Year::Number(y))             => time_to_timestamp(y, 1,                             1, 0, 0,   0),
-            ChangeTime::UntilMonth(Year::Number(y), m)         => time_to_timestamp(y, m as i8,                       1, 0, 0,   0),
-            ChangeTime::UntilDay(Year::Number(y), m, d)        => time_to_timestamp(y, m as i8, d.to_concrete_day(y, m), 0, 0,   0),
-            ChangeTime::UntilTime(Year::Number(y), m, d, time) => match time.0 {
-                TimeSpec::Zero                                 => time_to_timestamp(y, m as i8, d.to_concrete_day(y, m), 0, 0,   0),
-                TimeSpec::Hours(h)                             => time_to_timestamp(y, m as i8, d.to_concrete_day(y, m), h, 0,   0),
-                TimeSpec::HoursMinutes(h, min)                 => time_to_timestamp(y, m as i8, d.to_concrete_day(y, m), h, min, 0),
-                TimeSpec::HoursMinutesSeconds(h, min, s)       => time_to_timestamp(y, m as i8, d.to_concrete_day(y, m), h, min, s),
+            ChangeTime::UntilYear(Year::Number(y))             => time_to_timestamp(y, 1,        1, 0),
+            ChangeTime::UntilMonth(Year::Number(y), m)         => time_to_timestamp(y, m as i8, 1, 0),
+            ChangeTime::UntilDay(Year::Number(y), m, d) => {
+                let (y, m, d) = d.to_concrete_date(y, m);
+                time_to_timestamp(y, m as i8, d, 0)
+            },
+            ChangeTime::UntilTime(Year::Number(y), m, d, time) => {
+                let (y, m, d) = d.to_concrete_date(y, m);
+                time_to_timestamp(y, m as i8, d, time.0.as_seconds())
             },
             _ => unreachable!(),
         }
@@ -475,8 +819,10 @@ fn to_timestamp() {
     let time = ChangeTime::UntilYear(Year::Number(1900));
     assert_eq!(time.to_timestamp(), -2208988800);
     let time = ChangeTime::UntilTime(Year::Number(2000), Month::February, DaySpec::Last(Weekday::Sunday),
-        TimeSpecAndType(TimeSpec::Hours(9), TimeType::Wall));
+        TimeSpecAndType(TimeSpec::hours(9), TimeType::Wall));
     assert_eq!(time.to_timestamp(), 951642000);
+    let time = ChangeTime::UntilDay(Year::Number(2015), Month::February, DaySpec::FirstOnOrAfter(Weekday::Sunday, 29));
+    assert_eq!(time.to_timestamp(), 1425168000);
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
@@ -490,7 +836,7 @@ pub struct ZoneInfo<'a> {
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum Saving<'a> {
     NoSaving,
-    OneOff(TimeSpec),
+    OneOff(TimeSpecAndDst),
     Multiple(&'a str),
 }
 
@@ -502,7 +848,7 @@ pub struct Rule<'a> {
     pub month: Month,
     pub day: DaySpec,
     pub time: TimeSpecAndType,
-    pub time_to_add: TimeSpec,
+    pub time_to_add: TimeSpecAndDst,
     pub letters: Option<&'a str>,
 }
 
@@ -518,6 +864,37 @@ pub struct Link<'a> {
     pub new: &'a str,
 }
 
+/// Whether a leap second’s time is given in UTC, or in the local time of
+/// each zone it gets applied to.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum LeapType {
+    Rolling,
+    Stationary,
+}
+
+const LEAP_TYPE_NAMES: &[(&str, LeapType)] = &[
+    ("rolling",    LeapType::Rolling),
+    ("stationary", LeapType::Stationary),
+];
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Leap {
+    pub year: i64,
+    pub month: Month,
+    pub day: i8,
+    pub time: TimeSpec,
+    pub correction: i64,
+    pub leap_type: LeapType,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Expires {
+    pub year: i64,
+    pub month: Month,
+    pub day: i8,
+    pub time: TimeSpec,
+}
+
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum Line<'a> {
     Space,
@@ -525,6 +902,68 @@ pub enum Line<'a> {
     Continuation(ZoneInfo<'a>),
     Rule(Rule<'a>),
     Link(Link<'a>),
+    Leap(Leap),
+    Expires(Expires),
+}
+
+/// A parsed line, along with everything about it that parsing throws
+/// away, so that tools can show or rewrite a file without losing the
+/// comments in it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct AnnotatedLine<'a> {
+
+    /// The parsed contents of the line.
+    pub line: Line<'a>,
+
+    /// The line exactly as it was read, without its line terminator.
+    pub raw: &'a str,
+
+    /// The comment-only lines directly above this one, exactly as they
+    /// were read, in order.
+    pub leading_comments: Vec<&'a str>,
+
+    /// The comment at the end of this line, including the `#`.
+    pub trailing_comment: Option<&'a str>,
+}
+
+/// Returns the comment at the end of a matched line, including the `#`.
+fn comment<'a>(caps: &Captures<'a>) -> Option<&'a str> {
+    caps.name("comment").map(|c| c.as_str())
+}
+
+fn has_keyword(caps: &Captures, keyword: Keyword) -> bool {
+    let word = caps.name("keyword").unwrap().as_str();
+    lookup(word, KEYWORDS).or_else(|| lookup(word, LEAP_KEYWORDS)) == Some(keyword)
+}
+
+/// Whether a character can appear in a zone name read by a lenient parser.
+fn is_lenient_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '/' || c == '_' || c == '+' || c == '-'
+}
+
+/// Checks a zone or link name the way zic does before using it as a file
+/// name: it can’t begin or end with a slash or contain two in a row, and
+/// none of its components can be `.` or `..`.
+fn check_name(name: &str) -> Result<(), Error> {
+    if name.split('/').any(|c| c.is_empty() || c == "." || c == "..") {
+        Err(Error::InvalidZoneName(name.to_string()))
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Checks a zone’s format column the way zic does: a `%` has to be
+/// followed by `s` or `z`, and can’t appear twice, or alongside a `/`.
+fn check_format(format: &str) -> Result<(), Error> {
+    if let Some(index) = format.find('%') {
+        let rest = &format[index + 1 ..];
+        if !(rest.starts_with('s') || rest.starts_with('z')) || rest.contains('%') || format.contains('/') {
+            return Err(Error::InvalidFormat(format.to_string()));
+        }
+    }
+
+    Ok(())
 }
 
 fn parse_time_type(c: &str) -> Option<TimeType> {
@@ -537,34 +976,57 @@ fn parse_time_type(c: &str) -> Option<TimeType> {
 }
 
 impl LineParser {
+    /// Parses a time in any of the forms zic accepts: hours, optionally
+    /// followed by minutes, seconds, and a fraction of a second, with an
+    /// optional minus sign before and an optional time type suffix after.
+    /// The hours can go past 24, and fractions get rounded to the nearest
+    /// second, with halves rounded to an even second, as zic does.
     fn parse_timespec_and_type(&self, input: &str) -> Result<TimeSpecAndType, Error> {
         if input == "-" {
-            Ok(TimeSpecAndType(TimeSpec::Zero, TimeType::Wall))
+            return Ok(TimeSpec::ZERO.with_type(TimeType::Wall));
         }
-        else if input.chars().all(|c| c == '-' || c.is_digit(10)) {
-            Ok(TimeSpecAndType(TimeSpec::Hours(input.parse().unwrap()), TimeType::Wall))
-        }
-        else if let Some(caps) = self.hm_field.captures(input) {
-            let sign   : i8 = if caps.name("sign").unwrap().as_str() == "-" { -1 } else { 1 };
-            let hour   : i8 = caps.name("hour").unwrap().as_str().parse().unwrap();
-            let minute : i8 = caps.name("minute").unwrap().as_str().parse().unwrap();
-            let flag        = caps.name("flag").and_then(|c| parse_time_type(&c.as_str()[0..1]))
-                                          .unwrap_or(TimeType::Wall);
 
-            Ok(TimeSpecAndType(TimeSpec::HoursMinutes(hour * sign, minute * sign), flag))
+        let caps = match self.time_field.captures(input) {
+            Some(caps) => caps,
+            None       => return Err(Error::InvalidTimeSpecAndType(input.to_string())),
+        };
+
+        // The fields are all digits, so they can only fail to parse by
+        // being too long.
+        let field = |name| match caps.name(name) {
+            Some(m) => m.as_str().parse::<i64>().map_err(|_| Error::TimeOutOfRange(input.to_string())),
+            None    => Ok(0),
+        };
+
+        let hour       = field("hour")?;
+        let minute     = field("minute")?;
+        let mut second = field("second")?;
+
+        if minute >= 60 || second > 60 {
+            return Err(Error::InvalidTimeSpecAndType(input.to_string()));
         }
-        else if let Some(caps) = self.hms_field.captures(input) {
-            let sign   : i8 = if caps.name("sign").unwrap().as_str() == "-" { -1 } else { 1 };
-            let hour   : i8 = caps.name("hour").unwrap().as_str().parse().unwrap();
-            let minute : i8 = caps.name("minute").unwrap().as_str().parse().unwrap();
-            let second : i8 = caps.name("second").unwrap().as_str().parse().unwrap();
-            let flag        = caps.name("flag").and_then(|c| parse_time_type(&c.as_str()[0..1]))
-                                          .unwrap_or(TimeType::Wall);
 
-            Ok(TimeSpecAndType(TimeSpec::HoursMinutesSeconds(hour * sign, minute * sign, second * sign), flag))
-        } else {
-            Err(Error::InvalidTimeSpecAndType(input.to_string()))
+        if let Some(fraction) = caps.name("fraction") {
+            let digits = fraction.as_str().as_bytes();
+            let half_or_more = digits[0] >= b'5';
+            let exactly_half = digits[0] == b'5' && digits[1..].iter().all(|&d| d == b'0');
+            if half_or_more && !(exactly_half && second % 2 == 0) {
+                second += 1;
+            }
         }
+
+        // Keep the number of seconds well within the range of an `i32`, so
+        // that adding it to timestamps and offsets can’t overflow.
+        let seconds = hour.checked_mul(60 * 60)
+                          .and_then(|h| h.checked_add(minute * 60 + second))
+                          .filter(|s| *s <= i64::from(i32::MAX))
+                          .ok_or_else(|| Error::TimeOutOfRange(input.to_string()))?;
+
+        let sign = if caps.name("sign").unwrap().as_str() == "-" { -1 } else { 1 };
+        let flag = caps.name("flag").and_then(|c| parse_time_type(c.as_str()))
+                                    .unwrap_or(TimeType::Wall);
+
+        Ok(TimeSpec::from_seconds(sign * seconds).with_type(flag))
     }
 
     fn parse_timespec(&self, input: &str) -> Result<TimeSpec, Error> {
@@ -575,15 +1037,52 @@ impl LineParser {
         }
     }
 
+    /// Parses a UT offset or a SAVE amount, which, unlike the time of a
+    /// rule or an UNTIL, has to be less than a week either way. This is
+    /// the most that a POSIX TZ string can hold.
+    fn parse_offset(&self, input: &str) -> Result<TimeSpec, Error> {
+        const LIMIT: i64 = 167 * 60 * 60 + 59 * 60 + 59;
+
+        let spec = self.parse_timespec(input)?;
+        if spec.as_seconds().abs() > LIMIT {
+            return Err(Error::TimeOutOfRange(input.to_string()));
+        }
+
+        Ok(spec)
+    }
+
+    /// Parses a SAVE amount, which can have an `s` or `d` suffix to say
+    /// whether it’s standard or daylight-saving time, regardless of the
+    /// amount.
+    fn parse_save(&self, input: &str) -> Result<TimeSpecAndDst, Error> {
+        let (amount, is_dst) = if let Some(amount) = input.strip_suffix('s') {
+            (amount, Some(false))
+        }
+        else if let Some(amount) = input.strip_suffix('d') {
+            (amount, Some(true))
+        }
+        else {
+            (input, None)
+        };
+
+        Ok(TimeSpecAndDst(self.parse_offset(amount)?, is_dst))
+    }
+
     fn parse_dayspec(&self, input: &str) -> Result<DaySpec, Error> {
-        if input.chars().all(|c| c.is_digit(10)) {
-            Ok(DaySpec::Ordinal(input.parse().unwrap()))
-        } else if input.starts_with("last") {
-            let weekday = input[4..].parse()?;
+        if input.chars().all(|c| c.is_ascii_digit()) {
+            match input.parse() {
+                Ok(day) => Ok(DaySpec::Ordinal(day)),
+                Err(_)  => Err(Error::InvalidDaySpec(input.to_string())),
+            }
+        } else if input.len() > 4 && input.is_char_boundary(4) && input[.. 4].eq_ignore_ascii_case("last") {
+            let weekday = input[4 ..].parse()?;
             Ok(DaySpec::Last(weekday))
         } else if let Some(caps) = self.day_field.captures(input) {
-            let weekday = caps.name("weekday").unwrap().as_str().parse().unwrap();
-            let day     = caps.name("day").unwrap().as_str().parse().unwrap();
+            let weekday = caps.name("weekday").unwrap().as_str().parse()?;
+            let day     = match caps.name("day").unwrap().as_str().parse() {
+                Ok(day) => day,
+                Err(_)  => return Err(Error::InvalidDaySpec(input.to_string())),
+            };
 
             match caps.name("sign").unwrap().as_str() {
                 "<=" => Ok(DaySpec::LastOnOrBefore(weekday, day)),
@@ -595,46 +1094,73 @@ impl LineParser {
         }
     }
 
-    fn parse_rule<'a>(&self, input: &'a str) -> Result<Rule<'a>, Error> {
-        if let Some(caps) = self.rule_line.captures(input) {
+    /// Parses the day field that goes with the given month. Strict parsers
+    /// also check that the day falls within that month, in a leap year
+    /// unless told otherwise.
+    fn parse_day_of_month(&self, input: &str, month: Month, is_leap: bool) -> Result<DaySpec, Error> {
+        let day = self.parse_dayspec(input)?;
+        if self.strictness == Strictness::Strict {
+            let number = match day {
+                DaySpec::Ordinal(day)           => day,
+                DaySpec::LastOnOrBefore(_, day) => day,
+                DaySpec::FirstOnOrAfter(_, day) => day,
+                DaySpec::Last(_)                => return Ok(day),
+            };
+
+            if number < 1 || number > month.length(is_leap) {
+                return Err(Error::DayOutOfRange(input.to_string()));
+            }
+        }
+
+        Ok(day)
+    }
+
+    fn parse_rule<'a>(&self, input: &'a str) -> Result<(Rule<'a>, Option<&'a str>), Error> {
+        if let Some(caps) = self.rule_line.captures(input).filter(|c| has_keyword(c, Keyword::Rule)) {
             let name      = caps.name("name").unwrap().as_str();
             let from_year = caps.name("from").unwrap().as_str().parse()?;
 
             // The end year can be ‘only’ to indicate that this rule only
-            // takes place on that year.
-            let to_year = match caps.name("to").unwrap().as_str() {
-                "only"  => None,
-                to      => Some(to.parse()?),
+            // takes place on that year. Like the other keywords, it can be
+            // abbreviated.
+            let to = caps.name("to").unwrap().as_str();
+            let to_year = match lookup(to, &[("only", ())]) {
+                Some(()) => None,
+                None     => Some(to.parse()?),
             };
 
             // According to the spec, the only value inside the ‘type’ column
             // should be “-”, so throw an error if it isn’t. (It only exists
             // for compatibility with old versions that used to contain year
-            // types.) Sometimes “‐”, a Unicode hyphen, is used as well.
+            // types.) Sometimes “‐”, a Unicode hyphen, is used as well, which
+            // only lenient parsers let through.
             let t = caps.name("type").unwrap().as_str();
-            if t != "-" && t != "\u{2010}"  {
+            let unicode_hyphen = t == "\u{2010}" && self.strictness == Strictness::Lenient;
+            if t != "-" && !unicode_hyphen {
                 return Err(Error::TypeColumnContainedNonHyphen(t.to_string()));
             }
 
             let month        = caps.name("in").unwrap().as_str().parse()?;
-            let day          = self.parse_dayspec(caps.name("on").unwrap().as_str())?;
+            let day          = self.parse_day_of_month(caps.name("on").unwrap().as_str(), month, true)?;
             let time         = self.parse_timespec_and_type(caps.name("at").unwrap().as_str())?;
-            let time_to_add  = self.parse_timespec(caps.name("save").unwrap().as_str())?;
+            let time_to_add  = self.parse_save(caps.name("save").unwrap().as_str())?;
             let letters      = match caps.name("letters").unwrap().as_str() {
                 "-"  => None,
                 l    => Some(l),
             };
 
-            Ok(Rule {
-                name:         name,
-                from_year:    from_year,
-                to_year:      to_year,
-                month:        month,
-                day:          day,
-                time:         time,
-                time_to_add:  time_to_add,
-                letters:      letters,
-            })
+            let rule = Rule {
+                name,
+                from_year,
+                to_year,
+                month,
+                day,
+                time,
+                time_to_add,
+                letters,
+            };
+
+            Ok((rule, comment(&caps)))
         } else {
             Err(Error::NotParsedAsRuleLine)
         }
@@ -643,10 +1169,10 @@ impl LineParser {
     fn saving_from_str<'a>(&self, input: &'a str) -> Result<Saving<'a>, Error> {
         if input == "-" {
             Ok(Saving::NoSaving)
-        } else if input.chars().all(|c| c == '-' || c == '_' || c.is_alphabetic()) {
+        } else if !input.starts_with(|c: char| c == '-' || c == '+' || c.is_ascii_digit()) {
+            // Ruleset names can’t start with anything that a time could.
             Ok(Saving::Multiple(input))
-        } else if self.hm_field.is_match(input) {
-            let time = self.parse_timespec(input)?;
+        } else if let Ok(time) = self.parse_save(input) {
             Ok(Saving::OneOff(time))
         } else {
             Err(Error::CouldNotParseSaving(input.to_string()))
@@ -654,13 +1180,22 @@ impl LineParser {
     }
 
     fn zoneinfo_from_captures<'a>(&self, caps: Captures<'a>) -> Result<ZoneInfo<'a>, Error> {
-        let utc_offset = self.parse_timespec(caps.name("gmtoff").unwrap().as_str())?;
+        let utc_offset = self.parse_offset(caps.name("gmtoff").unwrap().as_str())?;
         let saving = self.saving_from_str(caps.name("rulessave").unwrap().as_str())?;
         let format = caps.name("format").unwrap().as_str();
+        if self.strictness == Strictness::Strict {
+            check_format(format)?;
+        }
 
         let time = match (caps.name("year"), caps.name("month"), caps.name("day"), caps.name("time")) {
-            (Some(y), Some(m), Some(d), Some(t)) => Some(ChangeTime::UntilTime  (y.as_str().parse()?, m.as_str().parse()?, self.parse_dayspec(d.as_str())?, self.parse_timespec_and_type(t.as_str())?)),
-            (Some(y), Some(m), Some(d), _      ) => Some(ChangeTime::UntilDay   (y.as_str().parse()?, m.as_str().parse()?, self.parse_dayspec(d.as_str())?)),
+            (Some(y), Some(m), Some(d), Some(t)) => {
+                let month = m.as_str().parse()?;
+                Some(ChangeTime::UntilTime(y.as_str().parse()?, month, self.parse_day_of_month(d.as_str(), month, true)?, self.parse_timespec_and_type(t.as_str())?))
+            },
+            (Some(y), Some(m), Some(d), _      ) => {
+                let month = m.as_str().parse()?;
+                Some(ChangeTime::UntilDay(y.as_str().parse()?, month, self.parse_day_of_month(d.as_str(), month, true)?))
+            },
             (Some(y), Some(m), _      , _      ) => Some(ChangeTime::UntilMonth (y.as_str().parse()?, m.as_str().parse()?)),
             (Some(y), _      , _      , _      ) => Some(ChangeTime::UntilYear  (y.as_str().parse()?)),
             (None   , None   , None   , None   ) => None,
@@ -668,62 +1203,162 @@ impl LineParser {
         };
 
         Ok(ZoneInfo {
-            utc_offset:  utc_offset,
-            saving:      saving,
-            format:      format,
-            time:        time,
+            utc_offset,
+            saving,
+            format,
+            time,
         })
     }
 
-    fn parse_zone<'a>(&self, input: &'a str) -> Result<Zone<'a>, Error> {
-        if let Some(caps) = self.zone_line.captures(input) {
+    fn parse_zone<'a>(&self, input: &'a str) -> Result<(Zone<'a>, Option<&'a str>), Error> {
+        if let Some(caps) = self.zone_line.captures(input).filter(|c| has_keyword(c, Keyword::Zone)) {
             let name = caps.name("name").unwrap().as_str();
+            match self.strictness {
+                Strictness::Lenient if !name.chars().all(is_lenient_name_char) => return Err(Error::NotParsedAsZoneLine),
+                Strictness::Strict => check_name(name)?,
+                _ => {},
+            }
+
+            let comment = comment(&caps);
             let info = self.zoneinfo_from_captures(caps)?;
-            Ok(Zone {
-                name: name,
-                info: info,
-            })
+            let zone = Zone {
+                name,
+                info,
+            };
+
+            Ok((zone, comment))
         } else {
             Err(Error::NotParsedAsZoneLine)
         }
     }
 
-    fn parse_link<'a>(&self, input: &'a str) -> Result<Link<'a>, Error> {
-        if let Some(caps) = self.link_line.captures(input) {
+    fn parse_link<'a>(&self, input: &'a str) -> Result<(Link<'a>, Option<&'a str>), Error> {
+        if let Some(caps) = self.link_line.captures(input).filter(|c| has_keyword(c, Keyword::Link)) {
             let target  = caps.name("target").unwrap().as_str();
             let name    = caps.name("name").unwrap().as_str();
-            Ok(Link { existing: target, new: name })
+            if self.strictness == Strictness::Strict {
+                check_name(name)?;
+            }
+
+            Ok((Link { existing: target, new: name }, comment(&caps)))
         }
         else {
             Err(Error::NotParsedAsLinkLine)
         }
     }
 
+    fn parse_leap_date(&self, caps: &Captures) -> Result<(i64, Month, i8, TimeSpec), Error> {
+        let year = caps.name("year").unwrap().as_str();
+        let year = match year.parse() {
+            Ok(year) => year,
+            Err(_)   => return Err(Error::FailedYearParse(year.to_string())),
+        };
+
+        let month = caps.name("month").unwrap().as_str().parse()?;
+        let day = match self.parse_day_of_month(caps.name("day").unwrap().as_str(), month, is_leap(year))? {
+            DaySpec::Ordinal(day) => day,
+            _ => return Err(Error::InvalidDaySpec(caps.name("day").unwrap().as_str().to_string())),
+        };
+
+        let time = self.parse_timespec(caps.name("time").unwrap().as_str())?;
+        Ok((year, month, day, time))
+    }
+
+    fn parse_leap<'a>(&self, input: &'a str) -> Result<(Leap, Option<&'a str>), Error> {
+        if let Some(caps) = self.leap_line.captures(input).filter(|c| has_keyword(c, Keyword::Leap)) {
+            let (year, month, day, time) = self.parse_leap_date(&caps)?;
+
+            let correction = match caps.name("correction").unwrap().as_str() {
+                "+"   => 1,
+                "-"   => -1,
+                other => return Err(Error::InvalidLeapCorrection(other.to_string())),
+            };
+
+            let leap_type = caps.name("type").unwrap().as_str();
+            let leap_type = match lookup(leap_type, LEAP_TYPE_NAMES) {
+                Some(t) => t,
+                None    => return Err(Error::InvalidLeapType(leap_type.to_string())),
+            };
+
+            Ok((Leap { year, month, day, time, correction, leap_type }, comment(&caps)))
+        }
+        else {
+            Err(Error::NotParsedAsLeapLine)
+        }
+    }
+
+    fn parse_expires<'a>(&self, input: &'a str) -> Result<(Expires, Option<&'a str>), Error> {
+        if let Some(caps) = self.expires_line.captures(input).filter(|c| has_keyword(c, Keyword::Expires)) {
+            let (year, month, day, time) = self.parse_leap_date(&caps)?;
+            Ok((Expires { year, month, day, time }, comment(&caps)))
+        }
+        else {
+            Err(Error::NotParsedAsExpiresLine)
+        }
+    }
+
+    /// Parses a line, throwing away any comment on it.
     pub fn parse_str<'a>(&self, input: &'a str) -> Result<Line<'a>, Error> {
-        if self.empty_line.is_match(input) {
-            return Ok(Line::Space)
+        self.parse_line(input).map(|(line, _)| line)
+    }
+
+    /// Parses a line, keeping its comment and original text alongside it.
+    /// A line that’s nothing but a comment comes back as `Line::Space`
+    /// with that comment. The leading comments are left empty, as they
+    /// live on other lines; `files::AnnotatedLines` fills them in.
+    pub fn parse_annotated<'a>(&self, input: &'a str) -> Result<AnnotatedLine<'a>, Error> {
+        let (line, trailing_comment) = self.parse_line(input)?;
+        Ok(AnnotatedLine {
+            line,
+            raw: input,
+            leading_comments: Vec::new(),
+            trailing_comment,
+        })
+    }
+
+    fn parse_line<'a>(&self, input: &'a str) -> Result<(Line<'a>, Option<&'a str>), Error> {
+        if let Some(caps) = self.empty_line.captures(input) {
+            return Ok((Line::Space, comment(&caps)))
         }
 
         match self.parse_zone(input) {
             Err(Error::NotParsedAsZoneLine) => {},
-            result => return result.map(Line::Zone),
-        }
-
-        match self.continuation_line.captures(input) {
-            None => {},
-            Some(caps) => return self.zoneinfo_from_captures(caps).map(Line::Continuation),
+            result => return result.map(|(zone, comment)| (Line::Zone(zone), comment)),
         }
 
         match self.parse_rule(input) {
             Err(Error::NotParsedAsRuleLine) => {},
-            result => return result.map(Line::Rule),
+            result => return result.map(|(rule, comment)| (Line::Rule(rule), comment)),
         }
 
         match self.parse_link(input) {
             Err(Error::NotParsedAsLinkLine) => {},
-            result => return result.map(Line::Link),
+            result => return result.map(|(link, comment)| (Line::Link(link), comment)),
+        }
+
+        match self.parse_leap(input) {
+            Err(Error::NotParsedAsLeapLine) => {},
+            result => return result.map(|(leap, comment)| (Line::Leap(leap), comment)),
+        }
+
+        match self.parse_expires(input) {
+            Err(Error::NotParsedAsExpiresLine) => {},
+            result => return result.map(|(expires, comment)| (Line::Expires(expires), comment)),
         }
-        
+
+        // Continuation lines are usually indented, but the compact
+        // tzdata.zi format leaves them flush with the margin, so an
+        // unindented line is accepted if it starts off like an offset.
+        let continues = input.starts_with(char::is_whitespace)
+                     || input.starts_with(|c: char| c == '-' || c.is_ascii_digit());
+
+        if continues {
+            if let Some(caps) = self.continuation_line.captures(input) {
+                let comment = comment(&caps);
+                return self.zoneinfo_from_captures(caps).map(|info| (Line::Continuation(info), comment));
+            }
+        }
+
         Err(Error::InvalidLineType(input.to_string()))
     }
 }
@@ -751,8 +1386,8 @@ mod tests {
         to_year:      Some(Year::Number(1973)),
         month:        Month::April,
         day:          DaySpec::Last(Weekday::Sunday),
-        time:         TimeSpec::HoursMinutes(2, 0).with_type(TimeType::Wall),
-        time_to_add:  TimeSpec::HoursMinutes(1, 0),
+        time:         TimeSpec::hours_minutes(2, 0).with_type(TimeType::Wall),
+        time_to_add:  TimeSpecAndDst(TimeSpec::hours_minutes(1, 0), None),
         letters:      Some("D"),
     })));
 
@@ -762,8 +1397,8 @@ mod tests {
         to_year:      None,
         month:        Month::October,
         day:          DaySpec::Ordinal(10),
-        time:         TimeSpec::HoursMinutes(2, 0).with_type(TimeType::Standard),
-        time_to_add:  TimeSpec::Hours(0),
+        time:         TimeSpec::hours_minutes(2, 0).with_type(TimeType::Standard),
+        time_to_add:  TimeSpecAndDst(TimeSpec::hours(0), None),
         letters:      None,
     })));
 
@@ -773,8 +1408,8 @@ mod tests {
         to_year:      Some(Year::Number(1980)),
         month:        Month::April,
         day:          DaySpec::FirstOnOrAfter(Weekday::Sunday, 1),
-        time:         TimeSpec::HoursMinutes(1, 0).with_type(TimeType::UTC),
-        time_to_add:  TimeSpec::HoursMinutes(1, 0),
+        time:         TimeSpec::hours_minutes(1, 0).with_type(TimeType::UTC),
+        time_to_add:  TimeSpecAndDst(TimeSpec::hours_minutes(1, 0), None),
         letters:      Some("S"),
     })));
 
@@ -784,22 +1419,22 @@ mod tests {
     test!(zone: "Zone  Australia/Adelaide  9:30    Aus         AC%sT   1971 Oct 31  2:00:00" => Ok(Line::Zone(Zone {
         name: "Australia/Adelaide",
         info: ZoneInfo {
-            utc_offset:  TimeSpec::HoursMinutes(9, 30),
+            utc_offset:  TimeSpec::hours_minutes(9, 30),
             saving:      Saving::Multiple("Aus"),
             format:      "AC%sT",
-            time:        Some(ChangeTime::UntilTime(Year::Number(1971), Month::October, DaySpec::Ordinal(31), TimeSpec::HoursMinutesSeconds(2, 0, 0).with_type(TimeType::Wall))),
+            time:        Some(ChangeTime::UntilTime(Year::Number(1971), Month::October, DaySpec::Ordinal(31), TimeSpec::hours_minutes_seconds(2, 0, 0).with_type(TimeType::Wall))),
         },
     })));
 
     test!(continuation_1: "                          9:30    Aus         AC%sT   1971 Oct 31  2:00:00" => Ok(Line::Continuation(ZoneInfo {
-        utc_offset:  TimeSpec::HoursMinutes(9, 30),
+        utc_offset:  TimeSpec::hours_minutes(9, 30),
         saving:      Saving::Multiple("Aus"),
         format:      "AC%sT",
-        time:        Some(ChangeTime::UntilTime(Year::Number(1971), Month::October, DaySpec::Ordinal(31), TimeSpec::HoursMinutesSeconds(2, 0, 0).with_type(TimeType::Wall))),
+        time:        Some(ChangeTime::UntilTime(Year::Number(1971), Month::October, DaySpec::Ordinal(31), TimeSpec::hours_minutes_seconds(2, 0, 0).with_type(TimeType::Wall))),
     })));
 
     test!(continuation_2: "			1:00	C-Eur	CE%sT	1943 Oct 25" => Ok(Line::Continuation(ZoneInfo {
-        utc_offset:  TimeSpec::HoursMinutes(1, 00),
+        utc_offset:  TimeSpec::hours_minutes(1, 00),
         saving:      Saving::Multiple("C-Eur"),
         format:      "CE%sT",
         time:        Some(ChangeTime::UntilDay(Year::Number(1943), Month::October, DaySpec::Ordinal(25))),
@@ -808,7 +1443,7 @@ mod tests {
     test!(zone_hyphen: "Zone Asia/Ust-Nera\t 9:32:54 -\tLMT\t1919" => Ok(Line::Zone(Zone {
         name: "Asia/Ust-Nera",
         info: ZoneInfo {
-            utc_offset:  TimeSpec::HoursMinutesSeconds(9, 32, 54),
+            utc_offset:  TimeSpec::hours_minutes_seconds(9, 32, 54),
             saving:      Saving::NoSaving,
             format:      "LMT",
             time:        Some(ChangeTime::UntilYear(Year::Number(1919))),
@@ -817,26 +1452,26 @@ mod tests {
 
     #[test]
     fn negative_offsets() {
-        static LINE: &'static str = "Zone    Europe/London   -0:01:15 -  LMT 1847 Dec  1  0:00s";
+        static LINE: &str = "Zone    Europe/London   -0:01:15 -  LMT 1847 Dec  1  0:00s";
         let parser = LineParser::new();
-        let zone = parser.parse_zone(LINE).unwrap();
-        assert_eq!(zone.info.utc_offset, TimeSpec::HoursMinutesSeconds(0, -1, -15));
+        let (zone, _) = parser.parse_zone(LINE).unwrap();
+        assert_eq!(zone.info.utc_offset, TimeSpec::hours_minutes_seconds(0, -1, -15));
     }
 
     #[test]
     fn negative_offsets_2() {
-        static LINE: &'static str = "Zone        Europe/Madrid   -0:14:44 -      LMT     1901 Jan  1  0:00s";
+        static LINE: &str = "Zone        Europe/Madrid   -0:14:44 -      LMT     1901 Jan  1  0:00s";
         let parser = LineParser::new();
-        let zone = parser.parse_zone(LINE).unwrap();
-        assert_eq!(zone.info.utc_offset, TimeSpec::HoursMinutesSeconds(0, -14, -44));
+        let (zone, _) = parser.parse_zone(LINE).unwrap();
+        assert_eq!(zone.info.utc_offset, TimeSpec::hours_minutes_seconds(0, -14, -44));
     }
 
     #[test]
     fn negative_offsets_3() {
-        static LINE: &'static str = "Zone America/Danmarkshavn -1:14:40 -    LMT 1916 Jul 28";
+        static LINE: &str = "Zone America/Danmarkshavn -1:14:40 -    LMT 1916 Jul 28";
         let parser = LineParser::new();
-        let zone = parser.parse_zone(LINE).unwrap();
-        assert_eq!(zone.info.utc_offset, TimeSpec::HoursMinutesSeconds(-1, -14, -40));
+        let (zone, _) = parser.parse_zone(LINE).unwrap();
+        assert_eq!(zone.info.utc_offset, TimeSpec::hours_minutes_seconds(-1, -14, -40));
     }
 
     test!(link: "Link  Europe/Istanbul  Asia/Istanbul" => Ok(Line::Link(Link {
@@ -850,6 +1485,184 @@ mod tests {
         assert_eq!(Month::from_str("December"), Ok(Month::December));
     }
 
+    #[test]
+    fn abbreviations() {
+        assert_eq!(Month::from_str("Ja"), Ok(Month::January));
+        assert_eq!(Month::from_str("F"), Ok(Month::February));
+        assert_eq!(Month::from_str("MAY"), Ok(Month::May));
+        assert_eq!(Month::from_str("Ju"), Err(Error::FailedMonthParse("ju".to_string())));
+        assert_eq!(Weekday::from_str("Su"), Ok(Weekday::Sunday));
+        assert_eq!(Weekday::from_str("Th"), Ok(Weekday::Thursday));
+        assert_eq!(Weekday::from_str("T"), Err(Error::FailedWeekdayParse("t".to_string())));
+        assert_eq!(Year::from_str("ma"), Ok(Year::Maximum));
+        assert_eq!(Year::from_str("mi"), Ok(Year::Minimum));
+        assert_eq!(Year::from_str("m"), Err(Error::FailedYearParse("m".to_string())));
+    }
+
+    test!(rule_abbreviated: "R d 1916 o - Jun 14 23s 1 S" => Ok(Line::Rule(Rule {
+        name:         "d",
+        from_year:    Year::Number(1916),
+        to_year:      None,
+        month:        Month::June,
+        day:          DaySpec::Ordinal(14),
+        time:         TimeSpec::hours(23).with_type(TimeType::Standard),
+        time_to_add:  TimeSpecAndDst(TimeSpec::hours(1), None),
+        letters:      Some("S"),
+    })));
+
+    test!(rule_abbreviated_2: "R E 1981 ma - Mar lastSu 1u 1 S" => Ok(Line::Rule(Rule {
+        name:         "E",
+        from_year:    Year::Number(1981),
+        to_year:      Some(Year::Maximum),
+        month:        Month::March,
+        day:          DaySpec::Last(Weekday::Sunday),
+        time:         TimeSpec::hours(1).with_type(TimeType::UTC),
+        time_to_add:  TimeSpecAndDst(TimeSpec::hours(1), None),
+        letters:      Some("S"),
+    })));
+
+    #[test]
+    fn wide_times() {
+        let parser = LineParser::new();
+        let seconds = |input| parser.parse_timespec_and_type(input).map(|t| (t.0.as_seconds(), t.1));
+
+        assert_eq!(seconds("24:00"),       Ok((24 * 3600, TimeType::Wall)));
+        assert_eq!(seconds("25:00s"),      Ok((25 * 3600, TimeType::Standard)));
+        assert_eq!(seconds("-167:59:59"),  Ok((-(167 * 3600 + 59 * 60 + 59), TimeType::Wall)));
+        assert_eq!(seconds("23:59:60"),    Ok((86400, TimeType::Wall)));
+        assert_eq!(seconds("2u"),          Ok((2 * 3600, TimeType::UTC)));
+        assert_eq!(seconds("-"),           Ok((0, TimeType::Wall)));
+    }
+
+    #[test]
+    fn fractional_seconds() {
+        let parser = LineParser::new();
+        let seconds = |input| parser.parse_timespec_and_type(input).map(|t| t.0.as_seconds());
+
+        assert_eq!(seconds("0:00:00.5"),   Ok(0));
+        assert_eq!(seconds("0:00:01.5"),   Ok(2));
+        assert_eq!(seconds("0:00:00.50"),  Ok(0));
+        assert_eq!(seconds("0:00:00.501"), Ok(1));
+        assert_eq!(seconds("0:00:00.4"),   Ok(0));
+        assert_eq!(seconds("-0:00:00.9"),  Ok(-1));
+    }
+
+    #[test]
+    fn invalid_times() {
+        let parser = LineParser::new();
+
+        assert_eq!(parser.parse_timespec_and_type("1:60"),                 Err(Error::InvalidTimeSpecAndType("1:60".to_string())));
+        assert_eq!(parser.parse_timespec_and_type("1-2"),                  Err(Error::InvalidTimeSpecAndType("1-2".to_string())));
+        assert_eq!(parser.parse_timespec_and_type("--1"),                  Err(Error::InvalidTimeSpecAndType("--1".to_string())));
+        assert_eq!(parser.parse_timespec_and_type("99999999999999999999"), Err(Error::TimeOutOfRange("99999999999999999999".to_string())));
+        assert_eq!(parser.parse_timespec_and_type("999999:00"),            Err(Error::TimeOutOfRange("999999:00".to_string())));
+    }
+
+    #[test]
+    fn offsets_under_a_week() {
+        let parser = LineParser::new();
+
+        assert!(parser.parse_zone("Zone Test/Big 167:59:59 - BIG").is_ok());
+        assert!(parser.parse_zone("Zone Test/Big -167:59:59 0:59 BIG").is_ok());
+        assert_eq!(parser.parse_zone("Zone Test/Big 168:00 - BIG"),    Err(Error::TimeOutOfRange("168:00".to_string())));
+        assert_eq!(parser.parse_zone("Zone Test/Big -99999:00 - BIG"), Err(Error::TimeOutOfRange("-99999:00".to_string())));
+        assert_eq!(parser.parse_zone("Zone Test/Big 0:00 200:00 BIG"), Err(Error::CouldNotParseSaving("200:00".to_string())));
+        assert_eq!(parser.parse_rule("Rule X 2000 only - Jan 1 0:00 168:00d X"), Err(Error::TimeOutOfRange("168:00".to_string())));
+
+        // Rule times don’t have the same limit.
+        let rule = parser.parse_rule("Rule X 2000 only - Jan 1 260:00 0 -").unwrap();
+        assert_eq!(rule.0.time.0.as_seconds(), 260 * 3600);
+    }
+
+    test!(save_standard: "Rule Namibia 1994 only - Mar 21 0:00 -1:00s WAT" => Ok(Line::Rule(Rule {
+        name:         "Namibia",
+        from_year:    Year::Number(1994),
+        to_year:      None,
+        month:        Month::March,
+        day:          DaySpec::Ordinal(21),
+        time:         TimeSpec::hours_minutes(0, 0).with_type(TimeType::Wall),
+        time_to_add:  TimeSpec::hours_minutes(-1, 0).with_dst(false),
+        letters:      Some("WAT"),
+    })));
+
+    test!(save_daylight: "Rule Eire 1971 only - Oct 31 2:00u 0d GMT" => Ok(Line::Rule(Rule {
+        name:         "Eire",
+        from_year:    Year::Number(1971),
+        to_year:      None,
+        month:        Month::October,
+        day:          DaySpec::Ordinal(31),
+        time:         TimeSpec::hours_minutes(2, 0).with_type(TimeType::UTC),
+        time_to_add:  TimeSpec::hours(0).with_dst(true),
+        letters:      Some("GMT"),
+    })));
+
+    test!(save_utc: "Rule EU 1981 max - Mar lastSun 1:00u 1:00u S" => Err(Error::NonWallClockInTimeSpec("1:00u".to_string())));
+
+    test!(zone_save_daylight: "Zone Europe/Dublin 1:00 0d GMT" => Ok(Line::Zone(Zone {
+        name: "Europe/Dublin",
+        info: ZoneInfo {
+            utc_offset:  TimeSpec::hours_minutes(1, 0),
+            saving:      Saving::OneOff(TimeSpec::hours(0).with_dst(true)),
+            format:      "GMT",
+            time:        None,
+        },
+    })));
+
+    test!(zone_abbreviated: "Z Africa/Algiers 0:12:12 - LMT 1891 Mar 16" => Ok(Line::Zone(Zone {
+        name: "Africa/Algiers",
+        info: ZoneInfo {
+            utc_offset:  TimeSpec::hours_minutes_seconds(0, 12, 12),
+            saving:      Saving::NoSaving,
+            format:      "LMT",
+            time:        Some(ChangeTime::UntilDay(Year::Number(1891), Month::March, DaySpec::Ordinal(16))),
+        },
+    })));
+
+    test!(continuation_unindented: "0:9:21 - PMT 1911 Mar 11" => Ok(Line::Continuation(ZoneInfo {
+        utc_offset:  TimeSpec::hours_minutes_seconds(0, 9, 21),
+        saving:      Saving::NoSaving,
+        format:      "PMT",
+        time:        Some(ChangeTime::UntilDay(Year::Number(1911), Month::March, DaySpec::Ordinal(11))),
+    })));
+
+    test!(continuation_one_off: "1 1 CEST 1940 F 25 2" => Ok(Line::Continuation(ZoneInfo {
+        utc_offset:  TimeSpec::hours(1),
+        saving:      Saving::OneOff(TimeSpecAndDst(TimeSpec::hours(1), None)),
+        format:      "CEST",
+        time:        Some(ChangeTime::UntilTime(Year::Number(1940), Month::February, DaySpec::Ordinal(25), TimeSpec::hours(2).with_type(TimeType::Wall))),
+    })));
+
+    test!(link_abbreviated: "L Africa/Abidjan Africa/Accra" => Ok(Line::Link(Link {
+        existing:  "Africa/Abidjan",
+        new:       "Africa/Accra",
+    })));
+
+    test!(abbreviated_keyword: "Lin Africa/Abidjan Africa/Accra" => Ok(Line::Link(Link {
+        existing:  "Africa/Abidjan",
+        new:       "Africa/Accra",
+    })));
+
+    test!(leap: "Leap\t2016\tDec\t31\t23:59:60\t+\tS" => Ok(Line::Leap(Leap {
+        year:        2016,
+        month:       Month::December,
+        day:         31,
+        time:        TimeSpec::hours_minutes_seconds(23, 59, 60),
+        correction:  1,
+        leap_type:   LeapType::Stationary,
+    })));
+
+    test!(expires: "Expires  2024  Dec  28  00:00:00  # comment" => Ok(Line::Expires(Expires {
+        year:   2024,
+        month:  Month::December,
+        day:    28,
+        time:   TimeSpec::hours_minutes_seconds(0, 0, 0),
+    })));
+
+    test!(bad_leap_correction: "Leap 2016 Dec 31 23:59:60 * S" => Err(Error::InvalidLeapCorrection("*".to_string())));
+    test!(bad_leap_type: "Leap 2016 Dec 31 23:59:60 + X" => Err(Error::InvalidLeapType("X".to_string())));
+
+    test!(unknown_keyword: "Lunk Africa/Abidjan Africa/Accra" => Err(Error::InvalidLineType("Lunk Africa/Abidjan Africa/Accra".to_string())));
+
     test!(golb: "GOLB" => Err(Error::InvalidLineType("GOLB".to_string())));
 
     test!(comment: "# this is a comment" => Ok(Line::Space));
@@ -862,8 +1675,99 @@ mod tests {
         new:       "Asia/Istanbul",
     })));
 
+    test!(comment_after_until: "\t\t\t0:00\t-\tGMT\t1968 # Greenwich" => Ok(Line::Continuation(ZoneInfo {
+        utc_offset:  TimeSpec::ZERO,
+        saving:      Saving::NoSaving,
+        format:      "GMT",
+        time:        Some(ChangeTime::UntilYear(Year::Number(1968))),
+    })));
+
     test!(two_comments_after: "Link  Europe/Istanbul  Asia/Istanbul   # comment ## comment" => Ok(Line::Link(Link {
         existing:  "Europe/Istanbul",
         new:       "Asia/Istanbul",
     })));
+
+    test!(lenient_unusual_name: "Zone Etc/GMT@1 1:00 - +01" => Err(Error::InvalidLineType("Zone Etc/GMT@1 1:00 - +01".to_string())));
+    test!(day_overflow: "Rule EU 1981 max - Mar 999 1:00u 1:00 S" => Err(Error::InvalidDaySpec("999".to_string())));
+
+    #[test]
+    fn strict_names() {
+        let parser = LineParser::with_strictness(Strictness::Strict);
+
+        assert_eq!(parser.parse_str("Zone Etc/GMT@1 1:00 - +01"), Ok(Line::Zone(Zone {
+            name: "Etc/GMT@1",
+            info: ZoneInfo {
+                utc_offset:  TimeSpec::hours(1),
+                saving:      Saving::NoSaving,
+                format:      "+01",
+                time:        None,
+            },
+        })));
+
+        assert_eq!(parser.parse_str("Zone Europe/../London 0:00 - GMT"), Err(Error::InvalidZoneName("Europe/../London".to_string())));
+        assert_eq!(parser.parse_str("Zone /London 0:00 - GMT"),          Err(Error::InvalidZoneName("/London".to_string())));
+        assert_eq!(parser.parse_str("Link Europe/London Europe//GB"),    Err(Error::InvalidZoneName("Europe//GB".to_string())));
+    }
+
+    #[test]
+    fn unknown_weekdays() {
+        let parser = LineParser::new();
+        assert_eq!(parser.parse_str("Rule EU 1981 max - Mar lastFoo 1:00u 1:00 S"), Err(Error::FailedWeekdayParse("foo".to_string())));
+        assert_eq!(parser.parse_str("Rule EU 1981 max - Mar Foo>=3 1:00u 1:00 S"),  Err(Error::FailedWeekdayParse("foo".to_string())));
+    }
+
+    #[test]
+    fn strict_days() {
+        let lenient = LineParser::new();
+        let strict = LineParser::with_strictness(Strictness::Strict);
+
+        let zero = "Rule EU 1981 max - Mar 0 1:00u 1:00 S";
+        assert!(lenient.parse_str(zero).is_ok());
+        assert_eq!(strict.parse_str(zero), Err(Error::DayOutOfRange("0".to_string())));
+
+        let weekday = "Rule EU 1981 max - Mar Sun>=32 1:00u 1:00 S";
+        assert!(lenient.parse_str(weekday).is_ok());
+        assert_eq!(strict.parse_str(weekday), Err(Error::DayOutOfRange("Sun>=32".to_string())));
+
+        assert!(strict.parse_str("Rule EU 1981 max - Feb 29 1:00u 1:00 S").is_ok());
+        assert_eq!(strict.parse_str("Zone Europe/London 0:00 - GMT 1971 Apr 31"), Err(Error::DayOutOfRange("31".to_string())));
+        assert_eq!(strict.parse_str("Leap 2015 Feb 29 23:59:60 + S"),            Err(Error::DayOutOfRange("29".to_string())));
+    }
+
+    #[test]
+    fn strict_formats() {
+        let lenient = LineParser::new();
+        let strict = LineParser::with_strictness(Strictness::Strict);
+
+        assert!(strict.parse_str("Zone Europe/London 0:00 GB G%sT").is_ok());
+        assert!(strict.parse_str("Zone Europe/London 0:00 GB GMT/BST").is_ok());
+
+        for format in &["G%dT", "%s%s", "%s/%z"] {
+            let line = format!("Zone Europe/London 0:00 GB {}", format);
+            assert!(lenient.parse_str(&line).is_ok());
+            assert_eq!(strict.parse_str(&line), Err(Error::InvalidFormat(format.to_string())));
+        }
+
+        let hyphen = "Rule EU 1981 max \u{2010} Mar lastSun 1:00u 1:00 S";
+        assert!(lenient.parse_str(hyphen).is_ok());
+        assert_eq!(strict.parse_str(hyphen), Err(Error::TypeColumnContainedNonHyphen("\u{2010}".to_string())));
+    }
+
+    #[test]
+    fn annotated() {
+        let parser = LineParser::new();
+
+        let line = parser.parse_annotated("Zone Indian/Mauritius 3:50:00 - LMT 1907 # Port Louis").unwrap();
+        assert_eq!(line.raw, "Zone Indian/Mauritius 3:50:00 - LMT 1907 # Port Louis");
+        assert_eq!(line.trailing_comment, Some("# Port Louis"));
+        assert_eq!(line.line, parser.parse_str(line.raw).unwrap());
+
+        let line = parser.parse_annotated("  # From Paul Eggert (2014-08-05):").unwrap();
+        assert_eq!(line.line, Line::Space);
+        assert_eq!(line.trailing_comment, Some("# From Paul Eggert (2014-08-05):"));
+
+        let line = parser.parse_annotated("\t\t\t4:00\tMauritius\t+04/+05").unwrap();
+        assert_eq!(line.trailing_comment, None);
+        assert!(line.leading_comments.is_empty());
+    }
 }
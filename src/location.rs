@@ -0,0 +1,30 @@
+//! The positions of lines within zoneinfo files.
+//!
+//! This lives apart from the `files` module, which does the reading, so
+//! that the `table` module can record where lines came from without
+//! depending on it.
+
+use std::fmt;
+use std::ops::Range;
+
+
+/// The position of a line within a zoneinfo file.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Location {
+
+    /// The name of the file the line was read from.
+    pub file: String,
+
+    /// The line number within that file, starting at 1.
+    pub line: usize,
+
+    /// The range of bytes within the file that the line occupies, not
+    /// including the line terminator.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
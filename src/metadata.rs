@@ -0,0 +1,444 @@
+//! Country and location metadata, from the `.tab` files.
+//!
+//! Alongside the files of rules, zones, and links, the tz database comes
+//! with a few tab-separated files describing where each zone is:
+//!
+//! - `iso3166.tab` maps two-letter ISO 3166 country codes to country names;
+//! - `zone1970.tab` lists every zone that has had distinct clocks since
+//!   1970, along with the codes of the countries that use it, the
+//!   coordinates of its principal location, and an optional comment;
+//! - `zone.tab` is an older version of `zone1970.tab`, with exactly one
+//!   country per line, which lists zones that are now just links.
+//!
+//! ```tz
+//! #codes  coordinates     TZ                      comments
+//! NZ,AQ   -3652+17446     Pacific/Auckland        New Zealand time
+//! NZ      -4357-17633     Pacific/Chatham         Chatham Islands
+//! ```
+//!
+//! These files get parsed into a `Metadata` value, which can then be
+//! queried by country or by zone, and checked against a `Table` to make
+//! sure that every zone it mentions actually exists.
+
+use std::collections::BTreeMap;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use files::{self, RawLines};
+use table::Table;
+
+
+/// The metadata from one or more `.tab` files.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Metadata {
+
+    /// Mapping of ISO 3166 country codes to country names.
+    pub countries: BTreeMap<String, String>,
+
+    /// Every entry from `zone1970.tab`, in the order they were read.
+    pub zones: Vec<ZoneEntry>,
+
+    /// Every entry from `zone.tab`, in the order they were read. These are
+    /// kept apart from `zones` because a release has both files, and most
+    /// zones are listed in each of them.
+    pub zone_tab: Vec<ZoneEntry>,
+}
+
+
+/// A line from `zone1970.tab` or `zone.tab`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ZoneEntry {
+
+    /// The codes of the countries that use this zone. The first one is the
+    /// country with the most people in the zone.
+    pub countries: Vec<String>,
+
+    /// The position of the zone’s principal location.
+    pub coordinates: Coordinates,
+
+    /// The name of the zone.
+    pub zone: String,
+
+    /// The human-readable comment describing this zone, if any.
+    pub comment: Option<String>,
+}
+
+
+/// A position on the globe, in decimal degrees.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Coordinates {
+
+    /// Degrees north of the equator, or negative for south.
+    pub latitude: f64,
+
+    /// Degrees east of the prime meridian, or negative for west.
+    pub longitude: f64,
+}
+
+impl Coordinates {
+
+    /// Decodes a position from the ISO 6709 format used in the `.tab`
+    /// files, which is either `±DDMM±DDDMM` or `±DDMMSS±DDDMMSS`.
+    pub fn parse(input: &str) -> Result<Coordinates, Error> {
+        let error = || Error::InvalidCoordinates(input.to_owned());
+
+        let split = match input.get(1 ..).and_then(|rest| rest.find(&['+', '-'][..])) {
+            Some(pos) => pos + 1,
+            None      => return Err(error()),
+        };
+
+        let latitude  = parse_degrees(&input[.. split], 2).ok_or_else(error)?;
+        let longitude = parse_degrees(&input[split ..], 3).ok_or_else(error)?;
+        if latitude.abs() > 90.0 || longitude.abs() > 180.0 {
+            return Err(error());
+        }
+
+        Ok(Coordinates { latitude, longitude })
+    }
+}
+
+/// Decodes one half of an ISO 6709 position, where the degrees take up
+/// the given number of digits.
+fn parse_degrees(input: &str, degree_digits: usize) -> Option<f64> {
+    let sign = match input.chars().next() {
+        Some('+') => 1.0,
+        Some('-') => -1.0,
+        _         => return None,
+    };
+
+    let digits = &input[1 ..];
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (degrees, minutes, seconds) = if digits.len() == degree_digits + 2 {
+        (&digits[.. degree_digits], &digits[degree_digits ..], "0")
+    }
+    else if digits.len() == degree_digits + 4 {
+        (&digits[.. degree_digits], &digits[degree_digits .. degree_digits + 2], &digits[degree_digits + 2 ..])
+    }
+    else {
+        return None;
+    };
+
+    let degrees: f64 = degrees.parse().ok()?;
+    let minutes: f64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    if minutes >= 60.0 || seconds >= 60.0 {
+        return None;
+    }
+
+    Some(sign * (degrees + minutes / 60.0 + seconds / 3600.0))
+}
+
+fn parse_country_code(input: &str) -> Result<String, Error> {
+    if input.len() == 2 && input.chars().all(|c| c.is_ascii_uppercase()) {
+        Ok(input.to_owned())
+    }
+    else {
+        Err(Error::InvalidCountryCode(input.to_owned()))
+    }
+}
+
+/// Splits a line into its tab-separated fields, or returns `None` if it’s
+/// blank or a comment.
+fn fields(line: &str) -> Option<Vec<&str>> {
+    if line.trim().is_empty() || line.starts_with('#') {
+        None
+    }
+    else {
+        Some(line.split('\t').collect())
+    }
+}
+
+impl Metadata {
+
+    /// Creates a new, empty set of metadata.
+    pub fn new() -> Metadata {
+        Metadata::default()
+    }
+
+    /// Reads `iso3166.tab` and `zone1970.tab` from the unpacked tzdata
+    /// release at the given path.
+    pub fn from_tzdata_dir<P: AsRef<Path>>(path: P) -> Result<Metadata, files::Error> {
+        let path = path.as_ref();
+        let mut metadata = Metadata::new();
+
+        let (name, contents) = read(&path.join("iso3166.tab"))?;
+        metadata.add_iso3166(&name, &contents)?;
+
+        let (name, contents) = read(&path.join("zone1970.tab"))?;
+        metadata.add_zone1970_tab(&name, &contents)?;
+
+        Ok(metadata)
+    }
+
+    /// Adds every country in the contents of an `iso3166.tab` file. The
+    /// file name is only used to report the locations of errors.
+    pub fn add_iso3166(&mut self, file: &str, contents: &str) -> Result<(), files::Error> {
+        for (location, line) in RawLines::new(file, contents) {
+            let fields = match fields(line) {
+                Some(fields) => fields,
+                None         => continue,
+            };
+
+            if fields.len() != 2 {
+                return Err(files::Error::Metadata(location, Error::WrongFieldCount(fields.len())));
+            }
+
+            match parse_country_code(fields[0]) {
+                Ok(code) => { let _ = self.countries.insert(code, fields[1].to_owned()); },
+                Err(e)   => return Err(files::Error::Metadata(location, e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds every zone in the contents of a `zone1970.tab` file, where each
+    /// line can list several comma-separated countries.
+    pub fn add_zone1970_tab(&mut self, file: &str, contents: &str) -> Result<(), files::Error> {
+        let entries = zone_entries(file, contents, true)?;
+        self.zones.extend(entries);
+        Ok(())
+    }
+
+    /// Adds every zone in the contents of a `zone.tab` file, where each
+    /// line has exactly one country.
+    pub fn add_zone_tab(&mut self, file: &str, contents: &str) -> Result<(), files::Error> {
+        let entries = zone_entries(file, contents, false)?;
+        self.zone_tab.extend(entries);
+        Ok(())
+    }
+
+    /// Returns the entries that queries by zone or country look through:
+    /// those from `zone1970.tab`, or from `zone.tab` if that’s the only
+    /// one that was read.
+    fn entries(&self) -> &[ZoneEntry] {
+        if self.zones.is_empty() { &self.zone_tab } else { &self.zones }
+    }
+
+    /// Returns the name of the country with the given code.
+    pub fn country_name(&self, code: &str) -> Option<&str> {
+        self.countries.get(code).map(|name| &**name)
+    }
+
+    /// Returns every zone used in the country with the given code.
+    pub fn zones_for_country(&self, code: &str) -> Vec<&ZoneEntry> {
+        self.entries().iter()
+            .filter(|entry| entry.countries.iter().any(|c| c == code))
+            .collect()
+    }
+
+    /// Returns the entry for the zone with the given name.
+    pub fn zone_entry(&self, zone: &str) -> Option<&ZoneEntry> {
+        self.entries().iter().find(|entry| entry.zone == zone)
+    }
+
+    /// Returns the code of the country that the zone with the given name
+    /// is in. For zones shared between countries, this is the one with the
+    /// most people in the zone.
+    pub fn country_of(&self, zone: &str) -> Option<&str> {
+        self.zone_entry(zone).map(|entry| &*entry.countries[0])
+    }
+
+    /// Checks this metadata against a table, returning every zone in
+    /// either file that the table doesn’t have a zone or link for, and
+    /// every country code that isn’t in the list of countries.
+    pub fn check(&self, table: &Table) -> Vec<Inconsistency> {
+        let mut problems = Vec::new();
+
+        for entry in self.zones.iter().chain(&self.zone_tab) {
+            if !table.zonesets.contains_key(&entry.zone) && !table.links.contains_key(&entry.zone) {
+                problems.push(Inconsistency::UnknownZone(entry.zone.clone()));
+            }
+
+            for code in &entry.countries {
+                if !self.countries.contains_key(code) {
+                    problems.push(Inconsistency::UnknownCountry(code.clone(), entry.zone.clone()));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+fn zone_entries(file: &str, contents: &str, multiple_countries: bool) -> Result<Vec<ZoneEntry>, files::Error> {
+    let mut entries = Vec::new();
+
+    for (location, line) in RawLines::new(file, contents) {
+        let fields = match fields(line) {
+            Some(fields) => fields,
+            None         => continue,
+        };
+
+        match zone_entry(&fields, multiple_countries) {
+            Ok(entry) => entries.push(entry),
+            Err(e)    => return Err(files::Error::Metadata(location, e)),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn zone_entry(fields: &[&str], multiple_countries: bool) -> Result<ZoneEntry, Error> {
+    if fields.len() != 3 && fields.len() != 4 {
+        return Err(Error::WrongFieldCount(fields.len()));
+    }
+
+    let countries = if multiple_countries {
+        fields[0].split(',').map(parse_country_code).collect::<Result<Vec<_>, _>>()?
+    }
+    else {
+        vec![ parse_country_code(fields[0])? ]
+    };
+
+    Ok(ZoneEntry {
+        countries,
+        coordinates: Coordinates::parse(fields[1])?,
+        zone:        fields[2].to_owned(),
+        comment:     fields.get(3).map(|&c| c.to_owned()),
+    })
+}
+
+fn read(path: &Path) -> Result<(String, String), files::Error> {
+    let name = path.display().to_string();
+    let mut contents = String::new();
+    match File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        Ok(_)  => Ok((name, contents)),
+        Err(e) => Err(files::Error::Io(name, e)),
+    }
+}
+
+
+/// A disagreement between the metadata and a table.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Inconsistency {
+
+    /// The metadata has an entry for a zone that the table has neither a
+    /// zone nor a link for.
+    UnknownZone(String),
+
+    /// A zone’s entry uses a country code, the first field, that isn’t in
+    /// the list of countries. The second field is the zone.
+    UnknownCountry(String, String),
+}
+
+
+/// Something that can go wrong while parsing a line of a `.tab` file.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+
+    /// The line had this many tab-separated fields, which is the wrong
+    /// number for the type of file.
+    WrongFieldCount(usize),
+
+    /// A country code wasn’t two capital letters.
+    InvalidCountryCode(String),
+
+    /// A set of coordinates wasn’t in either of the ISO 6709 forms.
+    InvalidCoordinates(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::WrongFieldCount(n)         => write!(f, "wrong number of fields ({})", n),
+            Error::InvalidCountryCode(ref s)  => write!(f, "invalid country code \"{}\"", s),
+            Error::InvalidCoordinates(ref s)  => write!(f, "invalid coordinates \"{}\"", s),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static ISO3166: &str = "\
+# ISO 3166 alpha-2 country codes
+AQ\tAntarctica
+NZ\tNew Zealand
+";
+
+    static ZONE1970: &str = "\
+#codes\tcoordinates\tTZ\tcomments
+NZ,AQ\t-3652+17446\tPacific/Auckland\tNew Zealand time
+NZ\t-4357-17633\tPacific/Chatham\tChatham Islands
+";
+
+    fn metadata() -> Metadata {
+        let mut metadata = Metadata::new();
+        metadata.add_iso3166("iso3166.tab", ISO3166).unwrap();
+        metadata.add_zone1970_tab("zone1970.tab", ZONE1970).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(Coordinates::parse("+4230+00131"), Ok(Coordinates { latitude: 42.5, longitude: 1.0 + 31.0 / 60.0 }));
+        assert_eq!(Coordinates::parse("-3652+17446"), Ok(Coordinates { latitude: -(36.0 + 52.0 / 60.0), longitude: 174.0 + 46.0 / 60.0 }));
+        assert_eq!(Coordinates::parse("+404251-0740023"), Ok(Coordinates { latitude: 40.0 + 42.0 / 60.0 + 51.0 / 3600.0, longitude: -(74.0 + 23.0 / 3600.0) }));
+        assert_eq!(Coordinates::parse("+4230"), Err(Error::InvalidCoordinates("+4230".to_owned())));
+        assert_eq!(Coordinates::parse("+423+00131"), Err(Error::InvalidCoordinates("+423+00131".to_owned())));
+        assert_eq!(Coordinates::parse("+4299+00131"), Err(Error::InvalidCoordinates("+4299+00131".to_owned())));
+    }
+
+    #[test]
+    fn queries() {
+        let metadata = metadata();
+        assert_eq!(metadata.country_name("NZ"), Some("New Zealand"));
+        assert_eq!(metadata.country_of("Pacific/Chatham"), Some("NZ"));
+        assert_eq!(metadata.zone_entry("Pacific/Chatham").unwrap().comment, Some("Chatham Islands".to_owned()));
+
+        let zones = metadata.zones_for_country("NZ").into_iter().map(|e| &*e.zone).collect::<Vec<_>>();
+        assert_eq!(zones, vec![ "Pacific/Auckland", "Pacific/Chatham" ]);
+        assert_eq!(metadata.zones_for_country("AQ").len(), 1);
+    }
+
+    #[test]
+    fn zone_tab() {
+        let mut metadata = Metadata::new();
+        metadata.add_zone_tab("zone.tab", "NZ\t-3652+17446\tPacific/Auckland\n").unwrap();
+        let error = metadata.add_zone_tab("zone.tab", "#\nNZ,AQ\t-3652+17446\tPacific/Auckland\n").unwrap_err();
+        assert_eq!(error.to_string(), "zone.tab:2: invalid country code \"NZ,AQ\"");
+    }
+
+    #[test]
+    fn zone_tab_and_zone1970_tab() {
+        let mut metadata = metadata();
+        metadata.add_zone_tab("zone.tab", "NZ\t-3652+17446\tPacific/Auckland\nAQ\t-7750+16636\tAntarctica/McMurdo\n").unwrap();
+
+        assert_eq!(metadata.zones.len(), 2);
+        assert_eq!(metadata.zone_tab.len(), 2);
+        assert_eq!(metadata.zones_for_country("NZ").len(), 2);
+        assert_eq!(metadata.zone_entry("Pacific/Auckland").unwrap().countries, vec![ "NZ", "AQ" ]);
+        assert_eq!(metadata.zone_entry("Antarctica/McMurdo"), None);
+
+        let mut only_zone_tab = Metadata::new();
+        only_zone_tab.add_zone_tab("zone.tab", "AQ\t-7750+16636\tAntarctica/McMurdo\n").unwrap();
+        assert_eq!(only_zone_tab.country_of("Antarctica/McMurdo"), Some("AQ"));
+    }
+
+    #[test]
+    fn check() {
+        let mut table = Table::default();
+        table.zonesets.insert("Pacific/Auckland".to_owned(), Vec::new());
+
+        let mut metadata = metadata();
+        metadata.add_zone1970_tab("zone1970.tab", "XX\t+0000+00000\tEtc/Nowhere\n").unwrap();
+
+        assert_eq!(metadata.check(&table), vec![
+            Inconsistency::UnknownZone("Pacific/Chatham".to_owned()),
+            Inconsistency::UnknownZone("Etc/Nowhere".to_owned()),
+            Inconsistency::UnknownCountry("XX".to_owned(), "Etc/Nowhere".to_owned()),
+        ]);
+    }
+}
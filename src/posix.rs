@@ -0,0 +1,507 @@
+//! Generating POSIX TZ strings for the ongoing rules of a zone.
+//!
+//! Fixed timespan sets only go up to a certain year, but many zones keep
+//! changing their clocks indefinitely, following rules that apply up until
+//! the `max` year. zic describes these ongoing changes with a *POSIX TZ
+//! string*, which gets written as the footer of a TZif file, and which
+//! readers use for every time after the last transition. For example, the
+//! string for `America/New_York` is:
+//!
+//! ```text
+//! EST5EDT,M3.2.0,M11.1.0
+//! ```
+//!
+//! This gives the standard time abbreviation and its offset (with the sign
+//! the opposite way round to the tz data files), the DST abbreviation, and
+//! the rules for when DST starts and ends: here, the second Sunday in March
+//! and the first Sunday in November, at the default time of 02:00.
+//!
+//! Abbreviations that aren’t entirely alphabetic, such as the ones produced
+//! by `%z`, get quoted in angle brackets, as in `<+0545>-5:45`.
+//!
+//! Only the last line of a zone is looked at, as that’s the one that’s in
+//! effect indefinitely. Not every zone can be expressed as a TZ string:
+//! the ongoing rules need to have exactly one change into DST and one
+//! change out of it each year, so a zone that fails this gets an error.
+//!
+//! The logic in this file is based off of the `stringzone` function in
+//! `zic.c`.
+
+use std::cmp::Ordering;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use line::{DaySpec, Month, TimeType, Year};
+use table::{Table, Saving, RuleInfo, ZoneInfo};
+use tzif::Version;
+
+
+/// A POSIX TZ string, along with the TZif version needed to hold it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TzString {
+
+    /// The TZ string itself, such as `EST5EDT,M3.2.0,M11.1.0`.
+    pub string: String,
+
+    /// The earliest TZif version whose footer can hold this string. This is
+    /// version 3 if the string uses the extensions to POSIX, such as times
+    /// outside 0 to 24 hours, and version 2 otherwise.
+    pub minimum_version: Version,
+}
+
+impl fmt::Display for TzString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.string)
+    }
+}
+
+
+/// Trait to put the `tz_string` method on Tables.
+pub trait TableTzString {
+
+    /// Computes the POSIX TZ string for the timezone with the given name.
+    /// Returns `None` if the table doesn’t contain a time zone with that
+    /// name, and an error if the zone can’t be expressed as a TZ string.
+    fn tz_string(&self, zone_name: &str) -> Option<Result<TzString, Error>>;
+}
+
+impl TableTzString for Table {
+    fn tz_string(&self, zone_name: &str) -> Option<Result<TzString, Error>> {
+        let zone_info = self.get_zoneset(zone_name)?.last()?;
+
+        let rules = match zone_info.saving {
+            Saving::Multiple(ref name) => match self.rulesets.get(name) {
+                Some(rules) => &rules[..],
+                None        => return Some(Err(Error::UnknownRuleset(name.clone()))),
+            },
+            _ => &[],
+        };
+
+        Some(tz_string(zone_info, rules))
+    }
+}
+
+
+/// Computes the POSIX TZ string for a zone line that’s in effect
+/// indefinitely, given the rules in the ruleset it refers to (which are
+/// ignored if it doesn’t refer to one).
+pub fn tz_string(zone_info: &ZoneInfo, rules: &[RuleInfo]) -> Result<TzString, Error> {
+    let mut extended = false;
+
+    let (std_rule, dst_rule) = match zone_info.saving {
+        Saving::NoSaving    => (None, None),
+        Saving::Multiple(_) => ongoing_rules(rules)?,
+
+        // A fixed saving never changes, so zic writes it as a zone with no
+        // DST at all, whose offset has the saving added on.
+        Saving::OneOff(save, is_dst) => {
+            let mut string = abbreviation(zone_info, save, is_dst, None);
+            string.push_str(&offset(-(zone_info.offset + save))?);
+            return Ok(TzString { string, minimum_version: Version::V2 });
+        },
+    };
+
+    // When no rules go on indefinitely, but the last one to apply was a
+    // change into DST, the zone stays on DST all year round. This gets
+    // written as a change into DST at the very start of the year, and a
+    // change out of it after the very end.
+    let (std_rule, dst_rule) = match (std_rule, dst_rule) {
+        (Some(rule), None) if rule.is_dst() => {
+            extended = true;
+            let letters = rules.iter()
+                               .filter(|r| !r.is_dst())
+                               .max_by(|a, b| compare_rules(a, b))
+                               .and_then(|r| r.letters.clone());
+
+            let dst = StringRule {
+                month: Month::January, day: DaySpec::Ordinal(1), time: 0, time_type: TimeType::Wall,
+                save: rule.time_to_add, letters: rule.letters.clone(),
+            };
+            let std = StringRule {
+                month: Month::December, day: DaySpec::Ordinal(31), time: 86400 + rule.time_to_add,
+                time_type: TimeType::Wall, save: 0, letters,
+            };
+            (Some(std), Some(dst))
+        },
+        (std, dst) => (std.map(StringRule::from), dst.map(StringRule::from)),
+    };
+
+    // A rule can be marked as standard time while still saving some
+    // amount, in which case that gets added on to the standard offset.
+    let std_save = std_rule.as_ref().map_or(0, |r| r.save);
+    let std_letters = std_rule.as_ref().and_then(|r| r.letters.as_ref());
+    let mut string = abbreviation(zone_info, std_save, false, std_letters);
+    string.push_str(&offset(-(zone_info.offset + std_save))?);
+
+    if let Some(dst_rule) = dst_rule {
+        let std_rule = std_rule.expect("Standard rule");
+        let save = dst_rule.save;
+
+        string.push_str(&abbreviation(zone_info, save, true, dst_rule.letters.as_ref()));
+        if save - std_save != 3600 {
+            string.push_str(&offset(-(zone_info.offset + save))?);
+        }
+
+        string.push(',');
+        string.push_str(&dst_rule.to_posix(std_save, zone_info.offset, &mut extended)?);
+        string.push(',');
+        string.push_str(&std_rule.to_posix(save, zone_info.offset, &mut extended)?);
+    }
+
+    Ok(TzString {
+        string,
+        minimum_version: if extended { Version::V3 } else { Version::V2 },
+    })
+}
+
+/// Picks out the rules that go on indefinitely: one changing into
+/// standard time, and one changing into DST. When no rules go on
+/// indefinitely, this returns the last rule to apply on its own.
+fn ongoing_rules(rules: &[RuleInfo]) -> Result<(Option<&RuleInfo>, Option<&RuleInfo>), Error> {
+    let mut std_rule = None;
+    let mut dst_rule = None;
+
+    for rule in rules.iter().filter(|r| r.years.is_ongoing()) {
+        let slot = if rule.is_dst() { &mut dst_rule } else { &mut std_rule };
+        if slot.is_some() {
+            return Err(Error::TooManyRules);
+        }
+
+        *slot = Some(rule);
+    }
+
+    match (std_rule, dst_rule) {
+        (None, None) => Ok((rules.iter().max_by(|a, b| compare_rules(a, b)), None)),
+        (Some(_), Some(_)) => Ok((std_rule, dst_rule)),
+        _ => Err(Error::UnpairedRule),
+    }
+}
+
+/// Orders rules by the last time they apply, as far as zic is concerned:
+/// by their last year, then month, then the day number written in the file.
+fn compare_rules(a: &RuleInfo, b: &RuleInfo) -> Ordering {
+    fn key(rule: &RuleInfo) -> (Year, Month, i8) {
+        let day = match rule.day {
+            DaySpec::Ordinal(day) | DaySpec::LastOnOrBefore(_, day) | DaySpec::FirstOnOrAfter(_, day) => day,
+            DaySpec::Last(_) => rule.month.length(true),
+        };
+
+        (rule.years.to, rule.month, day)
+    }
+
+    let (a, b) = (key(a), key(b));
+    a.0.cmp(&b.0)
+        .then((a.1 as u8).cmp(&(b.1 as u8)))
+        .then(a.2.cmp(&b.2))
+}
+
+/// Formats the abbreviation for a zone line with the given saving, DST
+/// flag, and letters, quoting it in angle brackets if it isn’t entirely alphabetic.
+fn abbreviation(zone_info: &ZoneInfo, save: i64, is_dst: bool, letters: Option<&String>) -> String {
+    let abbreviation = zone_info.format.format(zone_info.offset + save, is_dst, letters);
+
+    if !abbreviation.is_empty() && abbreviation.chars().all(|c| c.is_ascii_alphabetic()) {
+        abbreviation
+    }
+    else {
+        format!("<{}>", abbreviation)
+    }
+}
+
+/// Formats a number of seconds as a POSIX offset or time, leaving out the
+/// minutes and seconds when they’re zero.
+fn offset(seconds: i64) -> Result<String, Error> {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let abs = seconds.abs();
+    let (hours, minutes, secs) = (abs / 3600, abs / 60 % 60, abs % 60);
+
+    if hours >= 24 * 7 {
+        return Err(Error::OffsetTooLarge(seconds));
+    }
+
+    let mut string = format!("{}{}", sign, hours);
+    if minutes != 0 || secs != 0 {
+        string.push_str(&format!(":{:02}", minutes));
+        if secs != 0 {
+            string.push_str(&format!(":{:02}", secs));
+        }
+    }
+
+    Ok(string)
+}
+
+
+/// The parts of a rule that go into a TZ string.
+struct StringRule {
+    month: Month,
+    day: DaySpec,
+    time: i64,
+    time_type: TimeType,
+    save: i64,
+    letters: Option<String>,
+}
+
+impl<'a> From<&'a RuleInfo> for StringRule {
+    fn from(rule: &'a RuleInfo) -> StringRule {
+        StringRule {
+            month:      rule.month,
+            day:        rule.day,
+            time:       rule.time,
+            time_type:  rule.time_type,
+            save:       rule.time_to_add,
+            letters:    rule.letters.clone(),
+        }
+    }
+}
+
+impl StringRule {
+
+    /// Formats the date and time that this rule applies, given the amount
+    /// saved by the rule it takes over from and the zone’s standard
+    /// offset. Sets the flag if the result uses the extensions to POSIX.
+    fn to_posix(&self, previous_save: i64, std_offset: i64, extended: &mut bool) -> Result<String, Error> {
+        let mut time = self.time;
+
+        let mut string = match self.day {
+            DaySpec::Ordinal(day) => {
+                if self.month == Month::February && day == 29 {
+                    return Err(Error::February29);
+                }
+
+                // zic uses the zero-based form for January and February,
+                // as it’s shorter, and the one-based `J` form after that.
+                let before = self.month.days_before(false);
+                if self.month as u8 <= Month::February as u8 {
+                    format!("{}", before + i64::from(day) - 1)
+                }
+                else {
+                    format!("J{}", before + i64::from(day))
+                }
+            },
+
+            DaySpec::Last(weekday) => format!("M{}.5.{}", self.month as u8, weekday as u8),
+
+            DaySpec::FirstOnOrAfter(weekday, day) => {
+                let shift = i64::from(day - 1) % 7;
+                time += shift * 86400;
+                format!("M{}.{}.{}", self.month as u8, 1 + (day - 1) / 7, (weekday as i64 - shift + 7) % 7)
+            },
+
+            DaySpec::LastOnOrBefore(weekday, day) => {
+                if day == self.month.length(true) {
+                    format!("M{}.5.{}", self.month as u8, weekday as u8)
+                }
+                else {
+                    let shift = i64::from(day) % 7;
+                    time += shift * 86400;
+                    format!("M{}.{}.{}", self.month as u8, day / 7, (weekday as i64 - shift + 7) % 7)
+                }
+            },
+        };
+
+        // The time is written as a wall clock time before the change, so
+        // times in UTC or standard time need the offset, and the amount
+        // saved by the previous rule, added on.
+        if self.time_type == TimeType::UTC {
+            time += std_offset;
+        }
+        if self.time_type != TimeType::Wall {
+            time += previous_save;
+        }
+
+        if time != 2 * 3600 {
+            if !(0 .. 86400).contains(&time) {
+                *extended = true;
+            }
+
+            string.push('/');
+            string.push_str(&offset(time)?);
+        }
+
+        Ok(string)
+    }
+}
+
+
+/// Something that can stop a zone from being expressed as a TZ string.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+
+    /// The zone refers to a ruleset that isn’t in the table.
+    UnknownRuleset(String),
+
+    /// There are several rules going on indefinitely that change into
+    /// standard time, or several that change into DST.
+    TooManyRules,
+
+    /// There are rules going on indefinitely, but not both one that changes
+    /// into standard time and one that changes into DST.
+    UnpairedRule,
+
+    /// A rule applies on the 29th of February, which doesn’t exist every
+    /// year.
+    February29,
+
+    /// An offset or time, in seconds, is a week or more, which is too large
+    /// to write.
+    OffsetTooLarge(i64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownRuleset(ref name) => write!(f, "unknown ruleset {:?}", name),
+            Error::TooManyRules             => write!(f, "too many rules going on indefinitely"),
+            Error::UnpairedRule             => write!(f, "ongoing rules do not change both into and out of DST"),
+            Error::February29               => write!(f, "rule on February 29 cannot be expressed as a TZ string"),
+            Error::OffsetTooLarge(seconds)  => write!(f, "offset of {} seconds is too large for a TZ string", seconds),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use table::TableBuilder;
+    use line::LineParser;
+
+    fn table(lines: &[&str]) -> Table {
+        let parser = LineParser::new();
+        let mut builder = TableBuilder::new();
+        for line in lines {
+            builder.add_line(parser.parse_str(line).unwrap()).unwrap();
+        }
+
+        builder.build().unwrap()
+    }
+
+    fn tz_string(lines: &[&str], zone: &str) -> Result<TzString, Error> {
+        table(lines).tz_string(zone).unwrap()
+    }
+
+    #[test]
+    fn fixed() {
+        let result = tz_string(&["Zone Asia/Tokyo 9:00 - JST"], "Asia/Tokyo").unwrap();
+        assert_eq!(result.string, "JST-9");
+        assert_eq!(result.minimum_version, Version::V2);
+    }
+
+    #[test]
+    fn numeric_abbreviation() {
+        let result = tz_string(&["Zone Asia/Kathmandu 5:45 - %z"], "Asia/Kathmandu").unwrap();
+        assert_eq!(result.string, "<+0545>-5:45");
+
+        let result = tz_string(&["Zone America/Noronha -2:00 - -02"], "America/Noronha").unwrap();
+        assert_eq!(result.string, "<-02>2");
+    }
+
+    #[test]
+    fn new_york() {
+        let result = tz_string(&[
+            "Rule US 1967 2006 - Oct lastSun 2:00 0 S",
+            "Rule US 2007 max - Mar Sun>=8 2:00 1:00 D",
+            "Rule US 2007 max - Nov Sun>=1 2:00 0 S",
+            "Zone America/New_York -5:00 US E%sT",
+        ], "America/New_York").unwrap();
+        assert_eq!(result.string, "EST5EDT,M3.2.0,M11.1.0");
+        assert_eq!(result.minimum_version, Version::V2);
+    }
+
+    #[test]
+    fn london() {
+        let result = tz_string(&[
+            "Rule EU 1981 max - Mar lastSun 1:00u 1:00 S",
+            "Rule EU 1996 max - Oct lastSun 1:00u 0 -",
+            "Zone Europe/London 0:00 EU GMT/BST",
+        ], "Europe/London").unwrap();
+        assert_eq!(result.string, "GMT0BST,M3.5.0/1,M10.5.0");
+    }
+
+    #[test]
+    fn negative_dst() {
+        let result = tz_string(&[
+            "Rule Eire 1981 max - Mar lastSun 1:00u 0 -",
+            "Rule Eire 1996 max - Oct lastSun 1:00u -1:00 -",
+            "Zone Europe/Dublin 1:00 Eire IST/GMT",
+        ], "Europe/Dublin").unwrap();
+        assert_eq!(result.string, "IST-1GMT0,M10.5.0,M3.5.0/1");
+    }
+
+    #[test]
+    fn standard_time_saving() {
+        let result = tz_string(&[
+            "Rule X 2000 max - Mar lastSun 1:00u 2:00 D",
+            "Rule X 2000 max - Oct lastSun 1:00u 1:00s S",
+            "Zone Test/Zone 0:00 X A%sT",
+        ], "Test/Zone").unwrap();
+        assert_eq!(result.string, "AST-1ADT,M3.5.0,M10.5.0/3");
+    }
+
+    #[test]
+    fn extended_times() {
+        let result = tz_string(&[
+            "Rule Palestine 2016 max - Mar Sat<=30 2:00 1:00 S",
+            "Rule Palestine 2016 max - Oct lastSat 1:00 0 -",
+            "Zone Asia/Gaza 2:00 Palestine EE%sT",
+        ], "Asia/Gaza").unwrap();
+        assert_eq!(result.string, "EET-2EEST,M3.4.4/50,M10.5.6/1");
+        assert_eq!(result.minimum_version, Version::V3);
+    }
+
+    #[test]
+    fn permanent_dst() {
+        let result = tz_string(&[
+            "Rule Mor 2018 only - Mar 25 2:00 1:00 -",
+            "Zone Test/Permanent 0:00 Mor XXX/YYY",
+        ], "Test/Permanent").unwrap();
+        assert_eq!(result.string, "XXX0YYY,0/0,J365/25");
+        assert_eq!(result.minimum_version, Version::V3);
+    }
+
+    #[test]
+    fn finished_rules() {
+        let result = tz_string(&[
+            "Rule Mauritius 2008 only - Oct lastSun 2:00 1:00 S",
+            "Rule Mauritius 2009 only - Mar lastSun 2:00 0 -",
+            "Zone Indian/Mauritius 4:00 Mauritius MU%sT",
+        ], "Indian/Mauritius").unwrap();
+        assert_eq!(result.string, "MUT-4");
+    }
+
+    #[test]
+    fn unpaired() {
+        let result = tz_string(&[
+            "Rule Odd 2000 max - Mar lastSun 2:00 1:00 S",
+            "Zone Test/Odd 0:00 Odd X%sT",
+        ], "Test/Odd");
+        assert_eq!(result, Err(Error::UnpairedRule));
+    }
+
+    #[test]
+    fn fixed_saving() {
+        let result = tz_string(&["Zone Test/Summer 1:00 1:00 CEST"], "Test/Summer").unwrap();
+        assert_eq!(result.string, "CEST-2");
+
+        let result = tz_string(&["Zone Test/Marked 1:00 0d STD/DST"], "Test/Marked").unwrap();
+        assert_eq!(result.string, "DST-1");
+    }
+
+    #[test]
+    fn february_dates() {
+        let result = tz_string(&[
+            "Rule Feb 2000 max - Feb 20 2:00 1:00 S",
+            "Rule Feb 2000 max - Mar 20 2:00 0 -",
+            "Zone Test/February 0:00 Feb X%sT",
+        ], "Test/February").unwrap();
+        assert_eq!(result.string, "XT0XST,50,J79");
+    }
+
+    #[test]
+    fn unknown_zone() {
+        assert_eq!(table(&[]).tz_string("Nowhere"), None);
+    }
+}
@@ -39,11 +39,11 @@ use table::Table;
 pub trait Structure {
 
     /// Returns an iterator over the structure of this table.
-    fn structure(&self) -> TableStructure;
+    fn structure(&self) -> TableStructure<'_>;
 }
 
 impl Structure for Table {
-    fn structure(&self) -> TableStructure {
+    fn structure(&self) -> TableStructure<'_> {
         let mut mappings = BTreeMap::new();
 
         for key in self.zonesets.keys().chain(self.links.keys()) {
@@ -74,7 +74,7 @@ impl Structure for Table {
             }
         }
 
-        TableStructure { mappings: mappings }
+        TableStructure { mappings }
     }
 }
 
@@ -99,7 +99,7 @@ impl<'table> IntoIterator for TableStructure<'table> {
 
         Iter {
             structure: self,
-            keys: keys,
+            keys,
         }
     }
 }
@@ -115,20 +115,15 @@ impl<'table> Iterator for Iter<'table> {
     type Item = TableStructureEntry<'table>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let key = match self.keys.pop() {
-                Some(k) => k,
-                None    => return None,
-            };
+        let key = self.keys.pop()?;
 
-            // Move the strings out into an (automatically-sorted) vector.
-            let values = self.structure.mappings[key].iter().cloned().collect();
+        // Move the strings out into an (automatically-sorted) vector.
+        let values = self.structure.mappings[key].iter().cloned().collect();
 
-            return Some(TableStructureEntry {
-                name: key,
-                children: values,
-            });
-        }
+        Some(TableStructureEntry {
+            name: key,
+            children: values,
+        })
     }
 }
 
@@ -189,7 +184,7 @@ mod test {
         table.zonesets.insert("a/b".to_owned(), Vec::new());
 
         let mut structure = table.structure().into_iter();
-        assert_eq!(structure.next(), Some(TableStructureEntry { name: &"a".to_owned(), children: vec![ Child::TimeZone("b") ] }));
+        assert_eq!(structure.next(), Some(TableStructureEntry { name: "a", children: vec![ Child::TimeZone("b") ] }));
         assert_eq!(structure.next(), None);
     }
 
@@ -201,8 +196,8 @@ mod test {
         table.zonesets.insert("a/e".to_owned(),   Vec::new());
 
         let mut structure = table.structure().into_iter();
-        assert_eq!(structure.next(), Some(TableStructureEntry { name: &"a".to_owned(),   children: vec![ Child::Submodule("b"), Child::TimeZone("e") ] }));
-        assert_eq!(structure.next(), Some(TableStructureEntry { name: &"a/b".to_owned(), children: vec![ Child::TimeZone("c"),  Child::TimeZone("d") ] }));
+        assert_eq!(structure.next(), Some(TableStructureEntry { name: "a",   children: vec![ Child::Submodule("b"), Child::TimeZone("e") ] }));
+        assert_eq!(structure.next(), Some(TableStructureEntry { name: "a/b", children: vec![ Child::TimeZone("c"),  Child::TimeZone("d") ] }));
         assert_eq!(structure.next(), None);
     }
 }
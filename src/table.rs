@@ -2,7 +2,8 @@ use std::collections::hash_map::{HashMap, Entry};
 use std::error::Error as ErrorTrait;
 use std::fmt;
 
-use line::{self, Year, Month, DaySpec, ChangeTime, TimeType};
+use line::{self, Line, Year, Month, DaySpec, ChangeTime, TimeSpec, TimeSpecAndType, TimeType};
+use location::Location;
 
 /// A **table** of all the data in one or more zoneinfo files.
 #[derive(PartialEq, Debug, Default)]
@@ -22,17 +23,156 @@ pub struct Table {
 impl Table {
 
     /// Tries to find the zoneset with the given name by looking it up in
-    /// either the zonesets map or the links map.
+    /// either the zonesets map or the links map, following links to other
+    /// links until a zone is found.
     pub fn get_zoneset(&self, zone_name: &str) -> Option<&[ZoneInfo]> {
-        if self.zonesets.contains_key(zone_name) {
-            Some(&*self.zonesets[zone_name])
+        let name = self.canonical_name(zone_name)?;
+        self.zonesets.get(name).map(|zoneset| &**zoneset)
+    }
+
+    /// Returns the name of the zone that the given name refers to: either
+    /// the name itself, if it’s a zone, or the zone at the end of the chain
+    /// of links starting with it. A zone takes precedence over a link with
+    /// the same name.
+    ///
+    /// Returns `None` if there’s no zone or link with the name, or if the
+    /// chain of links ends at a missing zone or goes round in a cycle.
+    pub fn canonical_name<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if !self.zonesets.contains_key(name) && !self.links.contains_key(name) {
+            return None;
+        }
+
+        self.resolve_link(name).ok()
+    }
+
+    /// Follows the chain of links starting with the given name, which has
+    /// to be the name of a zone or link, returning the name of the zone it
+    /// ends at, or the error that stopped it.
+    fn resolve_link<'a>(&'a self, name: &'a str) -> Result<&'a str, Error> {
+        let mut chain = vec![ name ];
+        loop {
+            let current = chain[chain.len() - 1];
+            if self.zonesets.contains_key(current) {
+                return Ok(current);
+            }
+
+            let target = match self.links.get(current) {
+                Some(target) => &**target,
+                None => return Err(Error::DanglingLink(chain[chain.len() - 2].to_owned(), current.to_owned())),
+            };
+
+            if let Some(start) = chain.iter().position(|&n| n == target) {
+                let cycle = chain[start ..].iter().map(|&n| n.to_owned()).collect();
+                return Err(Error::LinkCycle(cycle));
+            }
+
+            chain.push(target);
         }
-        else if self.links.contains_key(zone_name) {
-            let target = &self.links[zone_name];
-            Some(&*self.zonesets[&*target])
+    }
+
+    /// Checks that every link leads to a zone, returning an error for each
+    /// link whose target is missing, and one for each cycle of links.
+    fn check_links(&self) -> Vec<Error> {
+        let mut names = self.links.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let mut errors = Vec::new();
+        for name in names {
+            let error = match self.resolve_link(name) {
+                Ok(_)   => continue,
+                Err(e)  => e,
+            };
+
+            // Only report each cycle once, starting with its first name,
+            // and leave out the links that merely lead into it.
+            if let Error::LinkCycle(ref cycle) = error {
+                if cycle[0] != *name || cycle.iter().any(|n| n < name) {
+                    continue;
+                }
+            }
+
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
         }
-        else {
-            None
+
+        errors
+    }
+
+    /// Rewrites every link to point directly at the zone at the end of its
+    /// chain, rather than at another link. Links that don’t lead to a zone
+    /// are left as they are.
+    pub fn flatten_links(&mut self) {
+        let targets = self.links.keys()
+                          .filter_map(|name| self.canonical_name(name).map(|zone| (name.clone(), zone.to_owned())))
+                          .collect::<Vec<_>>();
+
+        for (name, zone) in targets {
+            if name != zone {
+                let _ = self.links.insert(name, zone);
+            }
+        }
+    }
+
+    /// Rewrites every negative saving into the “rearguard” form that the
+    /// tz project publishes for software that can’t handle them, where the
+    /// standard offset is lowered by the most negative saving, and the
+    /// savings are raised by the same amount so that none of them are
+    /// negative. For example, Irish time becomes GMT in winter and IST as
+    /// daylight-saving time in summer, rather than IST as standard time in
+    /// summer and GMT with an hour taken away in winter.
+    ///
+    /// The total offsets, abbreviations, and transition instants all stay
+    /// the same; only which timespans count as daylight-saving time
+    /// changes. Rule times and zone UNTIL times given in standard time are
+    /// adjusted to match, as the standard offset they’re relative to moves.
+    pub fn convert_to_rearguard(&mut self) {
+        let mut shifts = HashMap::new();
+
+        for (name, rules) in &mut self.rulesets {
+            let lowest = rules.iter().map(|rule| rule.time_to_add).min().unwrap_or(0);
+            if lowest >= 0 {
+                continue;
+            }
+
+            // Rules without an `s` or `d` marker follow their new amount,
+            // and the ones with a marker swap it, to match the format.
+            for rule in rules.iter_mut() {
+                rule.time_to_add -= lowest;
+                rule.dst_marker = rule.dst_marker.map(|is_dst| !is_dst);
+                if rule.time_type == TimeType::Standard {
+                    rule.time += lowest;
+                }
+            }
+
+            let _ = shifts.insert(name.clone(), lowest);
+        }
+
+        for zone_info in self.zonesets.values_mut().flat_map(|zoneset| zoneset.iter_mut()) {
+            let lowest = match zone_info.saving {
+                Saving::Multiple(ref name) => match shifts.get(name) {
+                    Some(&lowest) => lowest,
+                    None          => continue,
+                },
+                Saving::OneOff(amount, _) if amount < 0 => {
+                    zone_info.saving = Saving::NoSaving;
+                    amount
+                },
+                _ => continue,
+            };
+
+            zone_info.offset += lowest;
+
+            if let Some(ChangeTime::UntilTime(year, month, day, TimeSpecAndType(time, TimeType::Standard))) = zone_info.end_time {
+                let time = TimeSpec::from_seconds(time.as_seconds() + lowest);
+                zone_info.end_time = Some(ChangeTime::UntilTime(year, month, day, time.with_type(TimeType::Standard)));
+            }
+
+            // What counted as daylight-saving time no longer does, and
+            // the other way round, so the halves of the format swap too.
+            if let Format::Alternate { ref mut standard, ref mut dst } = zone_info.format {
+                ::std::mem::swap(standard, dst);
+            }
         }
     }
 }
@@ -46,12 +186,8 @@ impl Table {
 #[derive(PartialEq, Debug)]
 pub struct RuleInfo {
 
-    /// The year that this rule *starts* applying.
-    pub from_year: Year,
-
-    /// The year that this rule *finishes* applying, inclusive, or `None` if
-    /// it applies up until the end of this timespan.
-    pub to_year: Option<Year>,
+    /// The years that this rule applies in.
+    pub years: YearRange,
 
     /// The month it applies on.
     pub month: Month,
@@ -68,6 +204,11 @@ pub struct RuleInfo {
     /// The amount of time to save.
     pub time_to_add: i64,
 
+    /// Whether the SAVE column had an `s` or `d` suffix saying that the
+    /// timespans this rule starts count as standard or daylight-saving
+    /// time, regardless of the amount.
+    pub dst_marker: Option<bool>,
+
     /// Any extra letters that should be added to this time zone’s
     /// abbreviation, in place of `%s`.
     pub letters: Option<String>,
@@ -76,30 +217,63 @@ pub struct RuleInfo {
 impl<'line> From<line::Rule<'line>> for RuleInfo {
     fn from(info: line::Rule) -> RuleInfo {
         RuleInfo {
-            from_year:    info.from_year,
-            to_year:      info.to_year,
+            years:        YearRange::new(info.from_year, info.to_year),
             month:        info.month,
             day:          info.day,
             time:         info.time.0.as_seconds(),
             time_type:    info.time.1,
-            time_to_add:  info.time_to_add.as_seconds(),
+            time_to_add:  info.time_to_add.0.as_seconds(),
+            dst_marker:   info.time_to_add.1,
             letters:      info.letters.map(str::to_owned),
         }
     }
 }
 
+/// The range of years that a rule applies in, inclusive at both ends.
+///
+/// Either end can be unbounded: a FROM year of `minimum` extends the rule
+/// back indefinitely, and a TO year of `maximum` extends it forward
+/// indefinitely. A TO year of `only` gives a range of just the FROM year.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct YearRange {
+
+    /// The first year the rule applies in.
+    pub from: Year,
+
+    /// The last year the rule applies in.
+    pub to: Year,
+}
+
+impl YearRange {
+
+    /// Creates a range from a rule’s FROM and TO years, where a missing TO
+    /// year means `only`.
+    pub fn new(from: Year, to: Option<Year>) -> YearRange {
+        YearRange { from, to: to.unwrap_or(from) }
+    }
+
+    /// Returns whether the given year is in this range.
+    pub fn contains(&self, year: i64) -> bool {
+        self.from <= Year::Number(year) && Year::Number(year) <= self.to
+    }
+
+    /// Returns whether this range has no years in it, because it ends
+    /// before it starts.
+    pub fn is_empty(&self) -> bool {
+        self.from > self.to
+    }
+
+    /// Returns whether this range goes on indefinitely.
+    pub fn is_ongoing(&self) -> bool {
+        self.to == Year::Maximum
+    }
+}
+
 impl RuleInfo {
 
     /// Returns whether this rule is in effect during the given year.
     pub fn applies_to_year(&self, year: i64) -> bool {
-        use line::Year::*;
-
-        match (self.from_year, self.to_year) {
-            (Number(from), None)             => year == from,
-            (Number(from), Some(Maximum))    => year >= from,
-            (Number(from), Some(Number(to))) => year >= from && year <= to,
-            _ => unreachable!(),
-        }
+        self.years.contains(year)
     }
 
     pub fn absolute_datetime(&self, year: i64, utc_offset: i64, dst_offset: i64) -> i64 {
@@ -113,6 +287,20 @@ impl RuleInfo {
         let changetime = ChangeTime::UntilDay(Year::Number(year), self.month, self.day);
         changetime.to_timestamp() + self.time - offset
     }
+
+    /// Whether the timespans this rule starts count as daylight-saving
+    /// time. Unless the SAVE column says otherwise with an `s` or `d`
+    /// suffix, this is whenever it saves a non-zero amount, including a
+    /// negative one.
+    pub fn is_dst(&self) -> bool {
+        self.dst_marker.unwrap_or(self.time_to_add != 0)
+    }
+
+    /// Generates the abbreviation for the timespans this rule starts on the
+    /// given zone line.
+    pub fn abbreviation(&self, zone_info: &ZoneInfo) -> String {
+        zone_info.format.format(zone_info.offset + self.time_to_add, self.is_dst(), self.letters.as_ref())
+    }
 }
 
 
@@ -149,7 +337,7 @@ impl<'line> From<line::ZoneInfo<'line>> for ZoneInfo {
             saving: match info.saving {
                 line::Saving::NoSaving     => Saving::NoSaving,
                 line::Saving::Multiple(s)  => Saving::Multiple(s.to_owned()),
-                line::Saving::OneOff(t)    => Saving::OneOff(t.as_seconds()),
+                line::Saving::OneOff(t)    => Saving::OneOff(t.0.as_seconds(), t.is_dst()),
             },
             format:   Format::new(info.format),
             end_time: info.time,
@@ -169,10 +357,11 @@ pub enum Saving {
     /// Just stick to the base offset.
     NoSaving,
 
-    /// This amount of time should be saved while this timespan is in effect.
-    /// (This is the equivalent to there being a single one-off rule with the
-    /// given amount of time to save).
-    OneOff(i64),
+    /// This amount of time should be saved while this timespan is in effect,
+    /// and it counts as daylight-saving time if the flag is set. (This is
+    /// the equivalent to there being a single one-off rule with the given
+    /// amount of time to save).
+    OneOff(i64, bool),
 
     /// All rules with the given name should apply while this timespan is in
     /// effect.
@@ -202,12 +391,17 @@ pub enum Format {
     /// A format with a placeholder `%s`, which uses the `letters` field in
     /// a `RuleInfo` to generate the time zone abbreviation.
     Placeholder(String),
+
+    /// A format with a placeholder `%z`, which uses the total offset from
+    /// UTC to generate a numeric time zone abbreviation, such as “+03” or
+    /// “-0330”.
+    Offset(String),
 }
 
 impl Format {
 
     /// Convert the template into one of the `Format` variants. This can’t
-    /// fail, as any syntax that doesn’t match one of the two formats will
+    /// fail, as any syntax that doesn’t match one of the other formats will
     /// just be a ‘constant’ format.
     pub fn new(template: &str) -> Format {
         if let Some(pos) = template.find('/') {
@@ -219,12 +413,22 @@ impl Format {
         else if template.contains("%s") {
             Format::Placeholder(template.to_owned())
         }
+        else if template.contains("%z") {
+            Format::Offset(template.to_owned())
+        }
         else {
             Format::Constant(template.to_owned())
         }
     }
 
-    pub fn format(&self, dst_offset: i64, letters: Option<&String>) -> String {
+    /// Generates the abbreviation for a timespan with the given total
+    /// offset from UTC, whether it counts as daylight-saving time, and the
+    /// letters of the rule that started it, if any.
+    ///
+    /// An alternate format picks its half based on the DST flag rather than
+    /// the sign of the offset, as zic does, so Irish time’s “IST/GMT” gives
+    /// “GMT” in winter, when an hour is taken away.
+    pub fn format(&self, offset: i64, is_dst: bool, letters: Option<&String>) -> String {
         let letters = match letters {
             Some(l) => &**l,
             None    => "",
@@ -233,7 +437,8 @@ impl Format {
         match *self {
             Format::Constant(ref s) => s.clone(),
             Format::Placeholder(ref s) => s.replace("%s", letters),
-            Format::Alternate { ref standard, .. } if dst_offset == 0 => standard.clone(),
+            Format::Offset(ref s) => s.replace("%z", &format_offset(offset)),
+            Format::Alternate { ref standard, .. } if !is_dst => standard.clone(),
             Format::Alternate { ref dst, .. } => dst.clone(),
         }
     }
@@ -248,6 +453,25 @@ impl Format {
     }
 }
 
+/// Formats an offset the way zic expands `%z`: a sign followed by two
+/// digits for the hours, then two for the minutes if they or the seconds
+/// aren’t zero, then two for the seconds if they aren’t zero.
+fn format_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+
+    if seconds != 0 {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+    else if minutes != 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    }
+    else {
+        format!("{}{:02}", sign, hours)
+    }
+}
+
 
 /// A builder for `Table` values based on various line definitions.
 #[derive(PartialEq, Debug)]
@@ -260,6 +484,20 @@ pub struct TableBuilder {
     /// `None` otherwise. This is so continuation lines can be added to the
     /// same zone as the original zone line.
     current_zoneset_name: Option<String>,
+
+    /// Every ruleset that a zone or continuation line has referred to, which
+    /// get checked once all the lines have been added.
+    references: Vec<UnresolvedRuleset>,
+
+    /// The zones that were still expecting a continuation line when
+    /// `end_zone` was called, which `build_lenient` leaves out.
+    unfinished: Vec<String>,
+}
+
+impl Default for TableBuilder {
+    fn default() -> Self {
+        TableBuilder::new()
+    }
 }
 
 impl TableBuilder {
@@ -269,27 +507,25 @@ impl TableBuilder {
         TableBuilder {
             table: Table::default(),
             current_zoneset_name: None,
+            references: Vec::new(),
+            unfinished: Vec::new(),
         }
     }
 
     /// Adds a new line describing a zone definition.
     ///
-    /// Returns an error if there’s already a zone with the same name, or the
-    /// zone refers to a ruleset that hasn’t been defined yet.
-    pub fn add_zone_line<'line>(&mut self, zone_line: line::Zone<'line>) -> Result<(), Error<'line>> {
-        if let line::Saving::Multiple(ruleset_name) = zone_line.info.saving {
-            if !self.table.rulesets.contains_key(ruleset_name) {
-                return Err(Error::UnknownRuleset(ruleset_name));
-            }
-        }
-
+    /// Returns an error if there’s already a zone with the same name. The
+    /// ruleset the zone refers to doesn’t have to have been defined yet, as
+    /// rulesets only get looked up when the table is built.
+    pub fn add_zone_line(&mut self, zone_line: line::Zone) -> Result<(), Error> {
         let zoneset: &mut _ = match self.table.zonesets.entry(zone_line.name.to_owned()) {
-            Entry::Occupied(_)  => return Err(Error::DuplicateZone),
+            Entry::Occupied(_)  => return Err(Error::DuplicateZone(zone_line.name.to_owned())),
             Entry::Vacant(e)    => e.insert(Vec::new()),
         };
 
         zoneset.push(zone_line.info.into());
         self.current_zoneset_name = Some(zone_line.name.to_owned());
+        self.add_reference(zone_line.name, &zone_line.info.saving);
         Ok(())
     }
 
@@ -298,21 +534,34 @@ impl TableBuilder {
     /// Returns an error if the builder wasn’t expecting a continuation line
     /// (meaning, the previous line wasn’t a zone line)
     pub fn add_continuation_line(&mut self, continuation_line: line::ZoneInfo) -> Result<(), Error> {
-        let zoneset: &mut _ = match self.current_zoneset_name {
-            Some(ref name) => self.table.zonesets.get_mut(name).unwrap(),
+        let name = match self.current_zoneset_name {
+            Some(ref name) => name.clone(),
             None => return Err(Error::SurpriseContinuationLine),
         };
 
-        zoneset.push(continuation_line.into());
+        self.add_reference(&name, &continuation_line.saving);
+        self.table.zonesets.get_mut(&name).unwrap().push(continuation_line.into());
         Ok(())
     }
 
+    /// Records the ruleset that a zone refers to, if any, so it can be
+    /// checked for when the table is built.
+    fn add_reference(&mut self, zone_name: &str, saving: &line::Saving) {
+        if let line::Saving::Multiple(ruleset_name) = *saving {
+            self.references.push(UnresolvedRuleset {
+                name:     ruleset_name.to_owned(),
+                zone:     zone_name.to_owned(),
+                location: None,
+            });
+        }
+    }
+
     /// Adds a new line describing one entry in a ruleset, creating that set
     /// if it didn’t exist already.
     pub fn add_rule_line(&mut self, rule_line: line::Rule) -> Result<(), Error> {
         let ruleset = self.table.rulesets
                                 .entry(rule_line.name.to_owned())
-                                .or_insert_with(Vec::new);
+                                .or_default();
 
         ruleset.push(rule_line.into());
         self.current_zoneset_name = None;
@@ -322,9 +571,9 @@ impl TableBuilder {
     /// Adds a new line linking one zone to another.
     ///
     /// Returns an error if there was already a link with that name.
-    pub fn add_link_line<'line>(&mut self, link_line: line::Link<'line>) -> Result<(), Error<'line>> {
+    pub fn add_link_line(&mut self, link_line: line::Link) -> Result<(), Error> {
         match self.table.links.entry(link_line.new.to_owned()) {
-            Entry::Occupied(_)  => Err(Error::DuplicateLink(link_line.new)),
+            Entry::Occupied(_)  => Err(Error::DuplicateLink(link_line.new.to_owned())),
             Entry::Vacant(e)    => {
                 let _ = e.insert(link_line.existing.to_owned());
                 self.current_zoneset_name = None;
@@ -333,43 +582,426 @@ impl TableBuilder {
         }
     }
 
+    /// Adds a parsed line of any type, dispatching to the method for that
+    /// type of line. Blank lines and comments are accepted and ignored, but
+    /// lines from the leap seconds file result in an error.
+    pub fn add_line(&mut self, line: Line) -> Result<(), Error> {
+        match line {
+            Line::Space              => Ok(()),
+            Line::Zone(zone)         => self.add_zone_line(zone),
+            Line::Continuation(info) => self.add_continuation_line(info),
+            Line::Rule(rule)         => self.add_rule_line(rule),
+            Line::Link(link)         => self.add_link_line(link),
+            Line::Leap(_)            => Err(Error::SurpriseLeapLine),
+            Line::Expires(_)         => Err(Error::SurpriseLeapLine),
+        }
+    }
+
+    /// Adds a parsed line of any type, as with `add_line`, recording the
+    /// location it was read from in case it refers to a ruleset that never
+    /// gets defined.
+    pub fn add_line_at(&mut self, line: Line, location: &Location) -> Result<(), Error> {
+        let count = self.references.len();
+        self.add_line(line)?;
+
+        for reference in &mut self.references[count ..] {
+            reference.location = Some(location.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the zone that the last line was part of, so that any
+    /// continuation lines that follow are rejected. This is used after a
+    /// line that couldn’t be parsed or added, as zic does, so that the rest
+    /// of a broken zone doesn’t end up being added to a different one.
+    ///
+    /// If the zone’s last line so far has an UNTIL time, the zone has been
+    /// cut short, and `build_lenient` will leave it out of the table.
+    pub fn end_zone(&mut self) {
+        if let Some(name) = self.current_zoneset_name.take() {
+            let cut_short = match self.table.zonesets[&name].last() {
+                Some(info) => info.end_time.is_some(),
+                None       => false,
+            };
+
+            if cut_short {
+                self.unfinished.push(name);
+            }
+        }
+    }
+
     /// Returns the table after it’s finished being built.
-    pub fn build(self) -> Table {
-        self.table
+    ///
+    /// Returns an error listing every zone line that refers to a ruleset
+    /// that was never defined, or otherwise an error for the first link
+    /// (in order of name) that doesn’t lead to a zone.
+    pub fn build(self) -> Result<Table, Error> {
+        let rulesets = &self.table.rulesets;
+        let unresolved = self.references.into_iter()
+                                         .filter(|r| !rulesets.contains_key(&r.name))
+                                         .collect::<Vec<_>>();
+
+        if !unresolved.is_empty() {
+            return Err(Error::UnknownRulesets(unresolved));
+        }
+
+        match self.table.check_links().into_iter().next() {
+            Some(error) => Err(error),
+            None        => Ok(self.table),
+        }
+    }
+
+    /// Returns the table after it’s finished being built, along with every
+    /// problem that would have made `build` fail. Rather than failing, the
+    /// zones that refer to rulesets that were never defined get left out of
+    /// the table, as do links that don’t lead to a zone.
+    ///
+    /// Zones that were cut short by `end_zone` get left out too, with an
+    /// error for each, as the lines that should have followed them were
+    /// skipped. Every other zone is kept, just as `build` would keep it.
+    pub fn build_lenient(self) -> (Table, Vec<Error>) {
+        let mut table = self.table;
+        let mut errors = Vec::new();
+
+        let unresolved = self.references.into_iter()
+                                        .filter(|r| !table.rulesets.contains_key(&r.name))
+                                        .collect::<Vec<_>>();
+
+        if !unresolved.is_empty() {
+            for reference in &unresolved {
+                let _ = table.zonesets.remove(&reference.zone);
+            }
+
+            errors.push(Error::UnknownRulesets(unresolved));
+        }
+
+        for name in self.unfinished {
+            let _ = table.zonesets.remove(&name);
+            errors.push(Error::UnfinishedZone(name));
+        }
+
+        errors.extend(table.check_links());
+        let broken = table.links.keys()
+                          .filter(|name| table.canonical_name(name).is_none())
+                          .cloned()
+                          .collect::<Vec<_>>();
+
+        for name in broken {
+            let _ = table.links.remove(&name);
+        }
+
+        (table, errors)
+    }
+}
+
+
+/// A zone line that refers to a ruleset that was never defined.
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnresolvedRuleset {
+
+    /// The name of the ruleset.
+    pub name: String,
+
+    /// The name of the zone whose line refers to it.
+    pub zone: String,
+
+    /// Where the line was read from, if it was added along with its
+    /// location.
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for UnresolvedRuleset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref location) = self.location {
+            write!(f, "{}: ", location)?;
+        }
+
+        write!(f, "zone {} refers to unknown ruleset {:?}", self.zone, self.name)
     }
 }
 
 
 /// Something that can go wrong while constructing a `Table`.
-#[derive(PartialEq, Debug, Copy, Clone)]
-pub enum Error<'line> {
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
 
     /// A continuation line was passed in, but the previous line wasn’t a zone
     /// definition line.
     SurpriseContinuationLine,
 
-    /// A zone definition referred to a ruleset that hadn’t been defined.
-    UnknownRuleset(&'line str),
+    /// Once all the lines had been added, some zone lines still referred to
+    /// rulesets that had never been defined.
+    UnknownRulesets(Vec<UnresolvedRuleset>),
 
     /// A link line was passed in, but there’s already a link with that name.
-    DuplicateLink(&'line str),
+    DuplicateLink(String),
 
     /// A zone line was passed in, but there’s already a zone with that name.
-    DuplicateZone,
+    DuplicateZone(String),
+
+    /// A leap or expires line was passed in, which only belong in the leap
+    /// seconds file.
+    SurpriseLeapLine,
+
+    /// A zone was cut short by a line that couldn’t be parsed or added, so
+    /// its last line has an UNTIL time with nothing saying what happens
+    /// after it.
+    UnfinishedZone(String),
+
+    /// A link, the first name, points to a zone or link, the second name,
+    /// that doesn’t exist.
+    DanglingLink(String, String),
+
+    /// A chain of links goes round in a cycle. This holds the names of the
+    /// links in the cycle, in order.
+    LinkCycle(Vec<String>),
 }
 
-impl<'line> fmt::Display for Error<'line> {
+impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
+        match *self {
+            Error::SurpriseContinuationLine    => write!(f, "continuation line does not follow a zone line"),
+            Error::DuplicateLink(ref name)     => write!(f, "link {} is defined more than once", name),
+            Error::DuplicateZone(ref name)     => write!(f, "zone {} is defined more than once", name),
+            Error::SurpriseLeapLine            => write!(f, "leap second line outside the leap seconds file"),
+            Error::UnfinishedZone(ref name)    => write!(f, "zone {} was cut short by a line that could not be added", name),
+            Error::DanglingLink(ref link, ref target) => write!(f, "link {} points to {}, which does not exist", link, target),
+            Error::LinkCycle(ref names)        => write!(f, "links form a cycle: {} -> {}", names.join(" -> "), names[0]),
+            Error::UnknownRulesets(ref list)   => {
+                for (i, unresolved) in list.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", unresolved)?;
+                }
+                Ok(())
+            },
+        }
     }
 }
 
-impl<'line> ErrorTrait for Error<'line> {
-    fn description(&self) -> &str {
-        "interpretation error"
+impl ErrorTrait for Error {
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use line::LineParser;
+
+    fn builder(lines: &[&str]) -> TableBuilder {
+        let parser = LineParser::new();
+        let mut builder = TableBuilder::new();
+        for line in lines {
+            builder.add_line(parser.parse_str(line).unwrap()).unwrap();
+        }
+
+        builder
+    }
+
+    #[test]
+    fn rules_after_zone() {
+        let table = builder(&[
+            "Zone Europe/London 0:00 GB-Eire %s 1968 Oct 27",
+            "                   1:00 - BST",
+            "Rule GB-Eire 1916 only - May 21 2:00s 1:00 BST",
+        ]).build().unwrap();
+
+        assert_eq!(table.zonesets["Europe/London"].len(), 2);
+        assert_eq!(table.rulesets["GB-Eire"].len(), 1);
+    }
+
+    #[test]
+    fn lenient_build() {
+        let (table, errors) = builder(&[
+            "Zone Europe/London 0:00 GB-Eire %s",
+            "Zone Europe/Dublin 0:00 - GMT",
+            "Link Europe/London GB",
+            "Link Europe/Dublin Eire",
+        ]).build_lenient();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1], Error::DanglingLink("GB".to_owned(), "Europe/London".to_owned()));
+        assert!(!table.zonesets.contains_key("Europe/London"));
+        assert!(!table.links.contains_key("GB"));
+        assert!(table.zonesets.contains_key("Europe/Dublin"));
+        assert!(table.links.contains_key("Eire"));
+    }
+
+    #[test]
+    fn lenient_unfinished_zones() {
+        let lines = [
+            "Zone Europe/London 0:00 - GMT 1968 Oct 27",
+            "Zone Europe/Dublin 0:00 - GMT 1916 May 21",
+        ];
+
+        let (table, errors) = builder(&lines).build_lenient();
+        assert!(errors.is_empty());
+        assert_eq!(table.zonesets.len(), 2);
+
+        let mut builder = builder(&lines);
+        builder.end_zone();
+        builder.end_zone();
+
+        let (table, errors) = builder.build_lenient();
+        assert_eq!(errors, vec![ Error::UnfinishedZone("Europe/Dublin".to_owned()) ]);
+        assert!(table.zonesets.contains_key("Europe/London"));
+        assert!(!table.zonesets.contains_key("Europe/Dublin"));
+    }
+
+    #[test]
+    fn link_chains() {
+        let mut table = builder(&[
+            "Zone Europe/London 0:00 - GMT",
+            "Link Europe/London GB",
+            "Link GB Europe/Belfast",
+            "Link Europe/Belfast NI",
+        ]).build().unwrap();
+
+        assert_eq!(table.canonical_name("NI"), Some("Europe/London"));
+        assert_eq!(table.canonical_name("Europe/London"), Some("Europe/London"));
+        assert_eq!(table.canonical_name("Europe/Paris"), None);
+        assert_eq!(table.get_zoneset("NI").unwrap().len(), 1);
+
+        table.flatten_links();
+        assert_eq!(table.links["NI"], "Europe/London");
+        assert_eq!(table.links["Europe/Belfast"], "Europe/London");
+    }
+
+    #[test]
+    fn dangling_link() {
+        let result = builder(&[
+            "Link Europe/London GB",
+            "Link GB Europe/Belfast",
+        ]).build();
+        assert_eq!(result, Err(Error::DanglingLink("GB".to_owned(), "Europe/London".to_owned())));
+    }
+
+    #[test]
+    fn link_cycle() {
+        let builder = builder(&[
+            "Link A B",
+            "Link B C",
+            "Link C A",
+            "Link A D",
+        ]);
+
+        let (table, errors) = builder.build_lenient();
+        assert_eq!(errors, vec![ Error::LinkCycle(vec![ "A".to_owned(), "C".to_owned(), "B".to_owned() ]) ]);
+        assert_eq!(errors[0].to_string(), "links form a cycle: A -> C -> B -> A");
+        assert!(table.links.is_empty());
+        assert_eq!(table.get_zoneset("D"), None);
+    }
+
+    #[test]
+    fn duplicate_zone() {
+        let parser = LineParser::new();
+        let mut builder = builder(&["Zone Europe/London 0:00 - GMT"]);
+        let error = builder.add_line(parser.parse_str("Zone Europe/London 1:00 - BST").unwrap()).unwrap_err();
+        assert_eq!(error, Error::DuplicateZone("Europe/London".to_owned()));
+        assert_eq!(error.to_string(), "zone Europe/London is defined more than once");
+    }
+
+    #[test]
+    fn unknown_rulesets() {
+        let result = builder(&[
+            "Zone Europe/London 0:00 GB-Eire %s 1968 Oct 27",
+            "                   1:00 BST-Rules BST",
+            "Zone Europe/Dublin 0:00 GB-Eire %s",
+        ]).build();
+
+        let names = match result {
+            Err(Error::UnknownRulesets(unresolved)) => unresolved.into_iter().map(|u| (u.name, u.zone)).collect::<Vec<_>>(),
+            r => panic!("Unexpected result {:?}", r),
+        };
+
+        assert_eq!(names, vec![
+            ("GB-Eire".to_owned(),   "Europe/London".to_owned()),
+            ("BST-Rules".to_owned(), "Europe/London".to_owned()),
+            ("GB-Eire".to_owned(),   "Europe/Dublin".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn offset_format() {
+        let format = Format::new("%z");
+        assert_eq!(format, Format::Offset("%z".to_owned()));
+        assert_eq!(format.format(-3 * 3600, false, None), "-03");
+        assert_eq!(format.format(-2 * 3600, true, None), "-02");
+        assert_eq!(format.format(-3 * 3600 - 1800, false, None), "-0330");
+        assert_eq!(format.format(5 * 3600 + 45 * 60, false, None), "+0545");
+        assert_eq!(format.format(-75, false, None), "-000115");
+        assert_eq!(format.format(0, false, None), "+00");
     }
 
-    fn cause(&self) -> Option<&ErrorTrait> {
-        None
+    #[test]
+    fn rearguard() {
+        use transitions::{TableTransitions, FixedTimespanSet};
+
+        fn span(set: &FixedTimespanSet, time: i64) -> (i64, i64, bool, String) {
+            let span = set.find_utc(time);
+            (span.total_offset(), span.dst_offset, span.is_dst, span.name.clone())
+        }
+
+        let lines = [
+            "Rule Eire 1971 only - Oct 31 2:00u -1:00 -",
+            "Rule Eire 1972 1980 - Mar Sun>=16 2:00u 0 -",
+            "Rule Eire 1972 1980 - Oct Sun>=23 2:00u -1:00 -",
+            "Zone Europe/Dublin 1:00 Eire IST/GMT",
+        ];
+
+        let winter = 60_393_600;  // 1971-12-01
+        let summer = 76_204_800;  // 1972-06-01
+
+        let vanguard = builder(&lines).build().unwrap().timespans("Europe/Dublin").unwrap();
+        assert_eq!(span(&vanguard, winter), (0,    -3600, true,  "GMT".to_owned()));
+        assert_eq!(span(&vanguard, summer), (3600,     0, false, "IST".to_owned()));
+
+        let mut table = builder(&lines).build().unwrap();
+        table.convert_to_rearguard();
+        assert_eq!(table.zonesets["Europe/Dublin"][0].offset, 0);
+
+        let rearguard = table.timespans("Europe/Dublin").unwrap();
+        assert_eq!(span(&rearguard, winter), (0,       0, false, "GMT".to_owned()));
+        assert_eq!(span(&rearguard, summer), (3600, 3600, true,  "IST".to_owned()));
+
+        // Explicit markers swap along with the format, so the result is
+        // the same as without them.
+        let marked = lines.iter().map(|line| line.replace(" -1:00 ", " -1:00d ").replace(" 0 ", " 0s ")).collect::<Vec<_>>();
+        let mut table = builder(&marked.iter().map(|line| &**line).collect::<Vec<_>>()).build().unwrap();
+        assert_eq!(table.rulesets["Eire"][1].dst_marker, Some(false));
+        table.convert_to_rearguard();
+        assert_eq!(table.rulesets["Eire"][1].dst_marker, Some(true));
+        assert!(table.rulesets["Eire"][1].is_dst());
+        assert_eq!(table.timespans("Europe/Dublin").unwrap(), rearguard);
+    }
+
+    #[test]
+    fn rearguard_standard_until() {
+        let mut table = builder(&[
+            "Rule Eire 1971 only - Oct 31 2:00u -1:00 -",
+            "Zone Europe/Dublin 1:00 Eire IST/GMT 1972 Jan 1 2:00s",
+            "                   0:00 - GMT",
+        ]).build().unwrap();
+
+        table.convert_to_rearguard();
+        let until = ChangeTime::UntilTime(Year::Number(1972), Month::January, DaySpec::Ordinal(1), TimeSpec::hours(1).with_type(TimeType::Standard));
+        assert_eq!(table.zonesets["Europe/Dublin"][0].end_time, Some(until));
+    }
+
+    #[test]
+    fn year_ranges() {
+        let only = YearRange::new(Year::Number(2000), None);
+        assert!(only.contains(2000) && !only.contains(1999) && !only.contains(2001));
+
+        let backwards = YearRange::new(Year::Minimum, Some(Year::Number(1990)));
+        assert!(backwards.contains(1800) && backwards.contains(1990) && !backwards.contains(1991));
+
+        let forwards = YearRange::new(Year::Number(2000), Some(Year::Maximum));
+        assert!(forwards.is_ongoing() && forwards.contains(9999) && !forwards.contains(1999));
+
+        assert!(YearRange::new(Year::Number(2000), Some(Year::Minimum)).is_empty());
+        assert!(!YearRange::new(Year::Minimum, None).is_empty());
     }
 }
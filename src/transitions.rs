@@ -12,11 +12,14 @@
 //! returned—so we may as well just return the vector, rather than an
 //! iterator over the vector.
 //!
-//! Similarly, there is a fixed set of years that is iterated over
-//! (currently 1800..2100), rather than having an iterator that produces
+//! Similarly, there is a fixed range of years that is iterated over (by
+//! default 1800 to 2099, or whatever range is given in a
+//! `TransitionOptions`), rather than having an iterator that produces
 //! timespans indefinitely. Not only do we need a complete set of timespans
 //! for sorting, but it is not necessarily advisable to rely on offset
-//! changes so far into the future!
+//! changes so far into the future! For zones whose ongoing changes can be
+//! described by a POSIX TZ string, the range can instead stop at the point
+//! that the string takes over.
 //!
 //! ### Example
 //!
@@ -101,7 +104,15 @@
 //! The logic in this file is based off of `zic.c`, which comes with the
 //! zoneinfo files and is in the public domain.
 
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::slice;
+
+use line::Year;
+use posix;
 use table::{Table, Saving, RuleInfo, ZoneInfo};
+use validate::until_key;
 
 
 /// A set of timespans, separated by the instances at which the timespans
@@ -125,12 +136,194 @@ pub struct FixedTimespanSet {
     pub rest: Vec<(i64, FixedTimespan)>,
 }
 
+impl FixedTimespanSet {
+
+    /// Returns the timespan in effect at the given Unix timestamp. A
+    /// timestamp at exactly a transition instant falls into the timespan
+    /// that the transition begins.
+    pub fn find_utc(&self, timestamp: i64) -> &FixedTimespan {
+        match self.rest.partition_point(|&(time, _)| time <= timestamp) {
+            0 => &self.first,
+            i => &self.rest[i - 1].1,
+        }
+    }
+
+    /// Returns an iterator over every transition in the set, in order.
+    pub fn transitions(&self) -> slice::Iter<'_, (i64, FixedTimespan)> {
+        self.rest.iter()
+    }
+
+    /// Returns an iterator over the transitions whose instants fall within
+    /// the given range of Unix timestamps, in order.
+    pub fn transitions_in<R: RangeBounds<i64>>(&self, range: R) -> slice::Iter<'_, (i64, FixedTimespan)> {
+        let start = match range.start_bound() {
+            Bound::Included(&t) => self.rest.partition_point(|&(time, _)| time < t),
+            Bound::Excluded(&t) => self.rest.partition_point(|&(time, _)| time <= t),
+            Bound::Unbounded    => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&t) => self.rest.partition_point(|&(time, _)| time <= t),
+            Bound::Excluded(&t) => self.rest.partition_point(|&(time, _)| time < t),
+            Bound::Unbounded    => self.rest.len(),
+        };
+
+        self.rest[start .. end.max(start)].iter()
+    }
+
+    /// Finds the Unix timestamps that the given local time maps to, where
+    /// the local time is given as a number of seconds since midnight at the
+    /// start of 1970, as shown on a wall clock in this zone.
+    ///
+    /// Usually there’s just the one, but when the clocks go back, local
+    /// times in the overlap happen twice, and when they go forward, local
+    /// times in the gap don’t happen at all.
+    pub fn find_local(&self, local: i64) -> LocalResult<'_> {
+
+        // A local time can only be in effect at UTC instants within the
+        // largest offset of it, so only the transitions within that window
+        // either side need to be looked at. Number the timespans so that 0
+        // is `first`, and `k` starts at `rest[k-1]`.
+        let window = self.rest.iter()
+            .map(|(_, span)| span.total_offset().saturating_abs())
+            .fold(self.first.total_offset().saturating_abs(), i64::max);
+        let low  = self.rest.partition_point(|&(time, _)| time <= local.saturating_sub(window));
+        let high = self.rest.partition_point(|&(time, _)| time <= local.saturating_add(window));
+
+        let span  = |k: usize| if k == 0 { &self.first } else { &self.rest[k - 1].1 };
+        let start = |k: usize| if k == 0 { None } else { Some(self.rest[k - 1].0) };
+        let end   = |k: usize| self.rest.get(k).map(|&(time, _)| time);
+        let utc   = |k: usize| local.saturating_sub(span(k).total_offset());
+        let mapping = |timestamp, k| LocalMapping { timestamp, timespan: span(k) };
+
+        let started_by = |timestamp, k| !matches!(start(k), Some(s) if timestamp < s);
+        let ends_after = |timestamp, k| !matches!(end(k), Some(e) if e <= timestamp);
+
+        let found = (low ..= high)
+            .map(|k| (utc(k), k))
+            .filter(|&(timestamp, k)| started_by(timestamp, k) && ends_after(timestamp, k))
+            .collect::<Vec<_>>();
+
+        match found.len() {
+            0 => {
+                // The local time falls on or after the start of the first
+                // timespan in the window, and before the end of the last
+                // one. The first timespan it falls before the end of must
+                // therefore start after it, and the transition into that
+                // timespan is the one that skips it.
+                let k = (low ..= high)
+                    .find(|&k| ends_after(utc(k), k))
+                    .unwrap_or(high)
+                    .max(1);
+
+                let before = mapping(utc(k), k - 1);
+                let after  = mapping(utc(k - 1), k);
+                LocalResult::Gap(before, after)
+            },
+            1 => LocalResult::Single(mapping(found[0].0, found[0].1)),
+            n => LocalResult::Ambiguous(mapping(found[0].0, found[0].1), mapping(found[n - 1].0, found[n - 1].1)),
+        }
+    }
+
+    /// Finds the Unix timestamp that the given local time maps to, using
+    /// the given policy for local times that happen twice or not at all.
+    /// Returns `None` if the policy rejects the local time.
+    pub fn resolve_local(&self, local: i64, policy: Disambiguation) -> Option<LocalMapping<'_>> {
+        self.find_local(local).resolve(policy)
+    }
+}
+
+
+/// A Unix timestamp that a local time maps to, along with the timespan in
+/// effect at that instant.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct LocalMapping<'a> {
+
+    /// The instant, as a Unix timestamp.
+    pub timestamp: i64,
+
+    /// The timespan in effect at that instant.
+    pub timespan: &'a FixedTimespan,
+}
+
+/// The result of looking up a local time in a `FixedTimespanSet`.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum LocalResult<'a> {
+
+    /// The local time happens exactly once.
+    Single(LocalMapping<'a>),
+
+    /// The local time happens twice, because the clocks went back: once
+    /// in the timespan before the transition, and once in the timespan
+    /// after it.
+    Ambiguous(LocalMapping<'a>, LocalMapping<'a>),
+
+    /// The local time doesn’t happen at all, because the clocks went
+    /// forward past it. This holds the instants the local time would have
+    /// mapped to had the transition not happened, using the offset of the
+    /// timespan after it (which gives an instant in the timespan before
+    /// the transition), and had it already happened, using the offset of
+    /// the timespan before it (which gives an instant in the timespan after
+    /// the transition).
+    ///
+    /// For example, 02:30 on the day New York moves from EST to EDT maps to
+    /// 01:30 EST and 03:30 EDT.
+    Gap(LocalMapping<'a>, LocalMapping<'a>),
+}
+
+impl<'a> LocalResult<'a> {
+
+    /// Picks a single instant out of this result using the given policy,
+    /// or returns `None` if the policy rejects it.
+    pub fn resolve(self, policy: Disambiguation) -> Option<LocalMapping<'a>> {
+        use self::Disambiguation::*;
+
+        match self {
+            LocalResult::Single(mapping) => Some(mapping),
+            LocalResult::Ambiguous(earlier, later) => match policy {
+                Earlier | ShiftForward => Some(earlier),
+                Later                  => Some(later),
+                Reject                 => None,
+            },
+            LocalResult::Gap(before, after) => match policy {
+                Earlier               => Some(before),
+                Later | ShiftForward  => Some(after),
+                Reject                => None,
+            },
+        }
+    }
+}
+
+/// A policy for picking an instant for a local time that happens twice or
+/// not at all.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Disambiguation {
+
+    /// Pick the earlier instant: the first time a repeated local time
+    /// happens, or the instant before a gap, shifting the local time
+    /// backwards by the length of the gap.
+    Earlier,
+
+    /// Pick the later instant: the second time a repeated local time
+    /// happens, or the instant after a gap, shifting the local time
+    /// forwards by the length of the gap.
+    Later,
+
+    /// Refuse to pick an instant for either.
+    Reject,
+
+    /// Pick the first time a repeated local time happens, but shift a
+    /// local time in a gap forwards by the length of the gap. This is what
+    /// most date and time libraries do.
+    ShiftForward,
+}
+
 
 /// An individual timespan with a fixed offset.
 ///
 /// This mimics the `FixedTimespan` struct in `datetime::cal::zone`, except
-/// instead of “total offset” and “is DST” fields, it has separate UTC and
-/// DST fields. Also, the name is an owned `String` here instead of a slice.
+/// instead of a “total offset” field, it has separate UTC and DST fields.
+/// Also, the name is an owned `String` here instead of a slice.
 #[derive(PartialEq, Debug, Clone)]
 pub struct FixedTimespan {
 
@@ -138,8 +331,16 @@ pub struct FixedTimespan {
     pub utc_offset: i64,
 
     /// The number of *extra* daylight-saving seconds during this timespan.
+    /// This can be negative, as with Irish time, where winter is the
+    /// daylight-saving period and has an hour taken away.
     pub dst_offset: i64,
 
+    /// Whether this timespan counts as daylight-saving time. Unless the
+    /// SAVE column says otherwise, this is set whenever the DST offset
+    /// isn’t zero, so it also holds for negative offsets, and isn’t
+    /// necessarily the period that is further ahead of UTC.
+    pub is_dst: bool,
+
     /// The abbreviation in use during this timespan.
     pub name: String,
 }
@@ -153,23 +354,159 @@ impl FixedTimespan {
 }
 
 
+/// Options for which years to generate transitions for.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct TransitionOptions {
+
+    /// The first year to apply rules in.
+    pub start_year: i64,
+
+    /// The year to stop applying rules after.
+    pub end: TransitionsEnd,
+}
+
+impl Default for TransitionOptions {
+    fn default() -> Self {
+        TransitionOptions {
+            start_year: 1800,
+            end:        TransitionsEnd::Year(2099),
+        }
+    }
+}
+
+/// The year to stop applying rules after.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TransitionsEnd {
+
+    /// The last year to apply rules in, inclusive.
+    Year(i64),
+
+    /// Stop once the zone’s POSIX TZ string can describe every transition
+    /// that follows, which is the year after the last one that any of its
+    /// lines or rules mention. This falls back to the given year for zones
+    /// that can’t be described by a TZ string.
+    UntilTzString(i64),
+}
+
+
 /// Trait to put the `timespans` method on Tables.
 pub trait TableTransitions {
 
-    /// Computes a fixed timespan set for the timezone with the given name.
+    /// Computes a fixed timespan set for the timezone with the given name,
+    /// using the default range of years.
+    /// Returns `None` if the table doesn’t contain a time zone with that name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zone’s data can’t be turned into timespans. Use
+    /// `try_timespans` to get an error instead.
+    fn timespans(&self, zone_name: &str) -> Option<FixedTimespanSet> {
+        self.timespans_with_options(zone_name, &TransitionOptions::default())
+    }
+
+    /// Computes a fixed timespan set for the timezone with the given name,
+    /// applying rules only in the years given in the options.
     /// Returns `None` if the table doesn’t contain a time zone with that name.
-    fn timespans(&self, zone_name: &str) -> Option<FixedTimespanSet>;
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zone’s data can’t be turned into timespans. Use
+    /// `try_timespans_with_options` to get an error instead.
+    fn timespans_with_options(&self, zone_name: &str, options: &TransitionOptions) -> Option<FixedTimespanSet>;
+
+    /// Computes a fixed timespan set for the timezone with the given name,
+    /// using the default range of years, or returns an error saying which
+    /// line of the zone stopped it from being computed.
+    fn try_timespans(&self, zone_name: &str) -> Result<FixedTimespanSet, Error> {
+        self.try_timespans_with_options(zone_name, &TransitionOptions::default())
+    }
+
+    /// Computes a fixed timespan set for the timezone with the given name,
+    /// applying rules only in the years given in the options, or returns an
+    /// error saying which line of the zone stopped it from being computed.
+    ///
+    /// The default implementation calls `timespans_with_options`, so it can
+    /// only report unknown zones as errors.
+    fn try_timespans_with_options(&self, zone_name: &str, options: &TransitionOptions) -> Result<FixedTimespanSet, Error> {
+        self.timespans_with_options(zone_name, options).ok_or_else(|| Error::UnknownZone(zone_name.to_owned()))
+    }
 }
 
 
+/// An error that stops a fixed timespan set from being computed. Zone lines
+/// are numbered from 1, with the zone line itself as line 1 and its
+/// continuation lines after it.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+
+    /// The table doesn’t contain a time zone with this name.
+    UnknownZone(String),
+
+    /// A zone line refers to a ruleset that isn’t in the table.
+    UnknownRuleset { zone: String, line: usize, ruleset: String },
+
+    /// A zone line other than the last one has no UNTIL time, so there’s
+    /// no way to tell when the next line takes over.
+    MissingUntil { zone: String, line: usize },
+
+    /// A zone line’s UNTIL time uses `minimum` or `maximum` as its year,
+    /// or names a day that doesn’t exist.
+    InvalidUntil { zone: String, line: usize },
+
+    /// A rule in the ruleset a zone line refers to names a day that doesn’t
+    /// exist in one of the years it applies to, such as February 29th in
+    /// a year that isn’t a leap year.
+    InvalidRuleDay { zone: String, line: usize, ruleset: String, year: i64 },
+
+    /// The zone starts off in daylight-saving time and never leaves it, so
+    /// there’s no standard time to use before its first transition.
+    NoStandardTime { zone: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownZone(ref zone)                                  => write!(f, "unknown zone {}", zone),
+            Error::UnknownRuleset { ref zone, line, ref ruleset }         => write!(f, "line {} of zone {} refers to unknown ruleset \"{}\"", line, zone, ruleset),
+            Error::MissingUntil { ref zone, line }                        => write!(f, "line {} of zone {} has no UNTIL time, but is not the last line", line, zone),
+            Error::InvalidUntil { ref zone, line }                        => write!(f, "line {} of zone {} has an invalid UNTIL time", line, zone),
+            Error::InvalidRuleDay { ref zone, line, ref ruleset, year }   => write!(f, "line {} of zone {} uses ruleset {}, which has a rule for a day that does not exist in {}", line, zone, ruleset, year),
+            Error::NoStandardTime { ref zone }                            => write!(f, "zone {} never uses standard time", zone),
+        }
+    }
+}
+
+impl ErrorTrait for Error {}
+
+
 impl TableTransitions for Table {
 
-    fn timespans(&self, zone_name: &str) -> Option<FixedTimespanSet> {
-        let mut builder = FixedTimespanSetBuilder::default();
+    fn timespans_with_options(&self, zone_name: &str, options: &TransitionOptions) -> Option<FixedTimespanSet> {
+        match self.try_timespans_with_options(zone_name, options) {
+            Ok(set)                    => Some(set),
+            Err(Error::UnknownZone(_)) => None,
+            Err(e)                     => panic!("{}", e),
+        }
+    }
+
+    fn try_timespans_with_options(&self, zone_name: &str, options: &TransitionOptions) -> Result<FixedTimespanSet, Error> {
+        let zoneset = self.get_zoneset(zone_name).ok_or_else(|| Error::UnknownZone(zone_name.to_owned()))?;
 
-        let zoneset = match self.get_zoneset(zone_name) {
-            Some(zones) => zones,
-            None => return None,
+        // Check the things that the code below assumes about each line up
+        // front, so that it can go on assuming them.
+        for (i, zone_info) in zoneset.iter().enumerate() {
+            check_zone_line(self, zone_name, i, zone_info, i != zoneset.len() - 1)?;
+        }
+
+        let end_year = match options.end {
+            TransitionsEnd::Year(year) => year,
+            TransitionsEnd::UntilTzString(fallback) => tz_string_year(self, zoneset).unwrap_or(fallback),
+        };
+
+        let mut builder = FixedTimespanSetBuilder {
+            start_year: options.start_year,
+            end_year,
+            .. FixedTimespanSetBuilder::default()
         };
 
         for (i, zone_info) in zoneset.iter().enumerate() {
@@ -181,19 +518,21 @@ impl TableTransitions for Table {
             let mut start_zone_id = None;
             let mut start_utc_offset = zone_info.offset;
             let mut start_dst_offset = 0;
+            let mut start_is_dst = false;
 
             match zone_info.saving {
                 Saving::NoSaving => {
-                    builder.add_fixed_saving(zone_info, 0, &mut dst_offset, utc_offset, &mut insert_start_transition, &mut start_zone_id);
+                    builder.add_fixed_saving(zone_info, 0, false, &mut dst_offset, utc_offset, &mut insert_start_transition, &mut start_zone_id);
                 },
 
-                Saving::OneOff(amount) => {
-                    builder.add_fixed_saving(zone_info, amount, &mut dst_offset, utc_offset, &mut insert_start_transition, &mut start_zone_id);
+                Saving::OneOff(amount, is_dst) => {
+                    builder.add_fixed_saving(zone_info, amount, is_dst, &mut dst_offset, utc_offset, &mut insert_start_transition, &mut start_zone_id);
                 },
 
-                Saving::Multiple(ref rules) => {
-                    let rules = &self.rulesets[&*rules];
-                    builder.add_multiple_saving(zone_info, &*rules, &mut dst_offset, use_until, utc_offset, &mut insert_start_transition, &mut start_zone_id, &mut start_utc_offset, &mut start_dst_offset);
+                Saving::Multiple(ref ruleset) => {
+                    let rules = &self.rulesets[ruleset];
+                    builder.add_multiple_saving(zone_info, rules, &mut dst_offset, use_until, utc_offset, &mut insert_start_transition, &mut start_zone_id, &mut start_utc_offset, &mut start_dst_offset, &mut start_is_dst)
+                           .map_err(|year| Error::InvalidRuleDay { zone: zone_name.to_owned(), line: i + 1, ruleset: ruleset.clone(), year })?;
                 }
             }
 
@@ -201,6 +540,7 @@ impl TableTransitions for Table {
                 let t = (builder.start_time.expect("Start time"), FixedTimespan {
                     utc_offset: start_utc_offset,
                     dst_offset: start_dst_offset,
+                    is_dst:     start_is_dst,
                     name:       start_zone_id.clone().expect("Start zone ID"),
                 });
                 builder.rest.push(t);
@@ -211,8 +551,68 @@ impl TableTransitions for Table {
             }
         }
 
-        Some(builder.build())
+        builder.build().ok_or_else(|| Error::NoStandardTime { zone: zone_name.to_owned() })
+    }
+}
+
+/// Checks that a zone line has everything needed to generate timespans
+/// from it, other than the days of its rules, which depend on the year.
+fn check_zone_line(table: &Table, zone_name: &str, i: usize, zone_info: &ZoneInfo, use_until: bool) -> Result<(), Error> {
+    let line = i + 1;
+
+    match zone_info.end_time {
+        Some(end_time) => {
+            if until_key(end_time).is_none() {
+                return Err(Error::InvalidUntil { zone: zone_name.to_owned(), line });
+            }
+        },
+        None => {
+            if use_until {
+                return Err(Error::MissingUntil { zone: zone_name.to_owned(), line });
+            }
+        },
+    }
+
+    if let Saving::Multiple(ref ruleset) = zone_info.saving {
+        if !table.rulesets.contains_key(ruleset) {
+            return Err(Error::UnknownRuleset { zone: zone_name.to_owned(), line, ruleset: ruleset.clone() });
+        }
     }
+
+    Ok(())
+}
+
+/// Returns the first year from which the zone’s POSIX TZ string describes
+/// all of its transitions, or `None` if it doesn’t have one, or none of its
+/// lines or rules mention a year to start from.
+fn tz_string_year(table: &Table, zoneset: &[ZoneInfo]) -> Option<i64> {
+    let last = zoneset.last()?;
+    let rules = match last.saving {
+        Saving::Multiple(ref name) => &table.rulesets.get(name)?[..],
+        _                          => &[],
+    };
+
+    if posix::tz_string(last, rules).is_err() {
+        return None;
+    }
+
+    let mut years = zoneset.iter()
+                           .filter_map(|info| info.end_time.map(|t| t.year()))
+                           .collect::<Vec<_>>();
+
+    for info in zoneset {
+        if let Saving::Multiple(ref name) = info.saving {
+            for rule in table.rulesets.get(name)? {
+                for year in &[rule.years.from, rule.years.to] {
+                    if let Year::Number(year) = *year {
+                        years.push(year);
+                    }
+                }
+            }
+        }
+    }
+
+    years.into_iter().max().map(|year| year + 1)
 }
 
 #[derive(Debug, Default)]
@@ -222,22 +622,27 @@ struct FixedTimespanSetBuilder {
 
     start_time: Option<i64>,
     until_time: Option<i64>,
+
+    start_year: i64,
+    end_year: i64,
 }
 
 impl FixedTimespanSetBuilder {
-    fn add_fixed_saving(&mut self, timespan: &ZoneInfo, amount: i64,
+    #[allow(clippy::too_many_arguments)]
+    fn add_fixed_saving(&mut self, timespan: &ZoneInfo, amount: i64, is_dst: bool,
             dst_offset: &mut i64, utc_offset: i64, insert_start_transition: &mut bool,
             start_zone_id: &mut Option<String>)
     {
         *dst_offset = amount;
-        *start_zone_id = Some(timespan.format.format(*dst_offset, None));
+        *start_zone_id = Some(timespan.format.format(timespan.offset + *dst_offset, is_dst, None));
 
         if *insert_start_transition {
             let time = self.start_time.unwrap();
             let timespan = FixedTimespan {
                 utc_offset: timespan.offset,
                 dst_offset: *dst_offset,
-                name:       start_zone_id.clone().unwrap_or_else(String::new),
+                is_dst,
+                name:       start_zone_id.clone().unwrap_or_default(),
             };
 
             self.rest.push((time, timespan));
@@ -245,21 +650,20 @@ impl FixedTimespanSetBuilder {
         }
         else {
             self.first = Some(FixedTimespan {
-                utc_offset: utc_offset,
+                utc_offset,
                 dst_offset: *dst_offset,
-                name:       start_zone_id.clone().unwrap_or_else(String::new),
+                is_dst,
+                name:       start_zone_id.clone().unwrap_or_default(),
             });
         }
     }
 
-    #[allow(unused_results)]
+    #[allow(unused_results, clippy::too_many_arguments)]
     fn add_multiple_saving(&mut self, timespan: &ZoneInfo, rules: &[RuleInfo],
             dst_offset: &mut i64, use_until: bool, utc_offset: i64, insert_start_transition: &mut bool,
-            start_zone_id: &mut Option<String>, start_utc_offset: &mut i64, start_dst_offset: &mut i64)
+            start_zone_id: &mut Option<String>, start_utc_offset: &mut i64, start_dst_offset: &mut i64, start_is_dst: &mut bool) -> Result<(), i64>
     {
-        use std::mem::replace;
-
-        for year in 1800..2100 {
+        for year in self.start_year ..= self.end_year {
             if use_until && year > timespan.end_time.unwrap().year() {
                 break;
             }
@@ -268,6 +672,12 @@ impl FixedTimespanSetBuilder {
                                            .filter(|r| r.applies_to_year(year))
                                            .collect::<Vec<_>>();
 
+            // The rules’ days get turned into timestamps below, which
+            // assumes they’re valid in this year.
+            if activated_rules.iter().any(|r| r.day.checked_concrete_date(year, r.month).is_none()) {
+                return Err(year);
+            }
+
             loop {
                 if use_until {
                     self.until_time = Some(timespan.end_time.unwrap().to_timestamp() - utc_offset - *dst_offset);
@@ -298,42 +708,47 @@ impl FixedTimespanSetBuilder {
 
                 if *insert_start_transition {
                     if earliest_at < self.start_time.unwrap() {
-                        replace(start_utc_offset, timespan.offset);
-                        replace(start_dst_offset, *dst_offset);
-                        replace(start_zone_id, Some(timespan.format.format(*dst_offset, earliest_rule.letters.as_ref())));
+                        *start_utc_offset = timespan.offset;
+                        *start_dst_offset = *dst_offset;
+                        *start_is_dst = earliest_rule.is_dst();
+                        *start_zone_id = Some(earliest_rule.abbreviation(timespan));
                         continue;
                     }
 
                     if start_zone_id.is_none() && *start_utc_offset + *start_dst_offset == timespan.offset + *dst_offset {
-                        replace(start_zone_id, Some(timespan.format.format(*dst_offset, earliest_rule.letters.as_ref())));
+                        *start_zone_id = Some(earliest_rule.abbreviation(timespan));
                     }
                 }
 
                 let t = (earliest_at, FixedTimespan {
                     utc_offset: timespan.offset,
                     dst_offset: earliest_rule.time_to_add,
-                    name:       timespan.format.format(earliest_rule.time_to_add, earliest_rule.letters.as_ref()),
+                    is_dst:     earliest_rule.is_dst(),
+                    name:       earliest_rule.abbreviation(timespan),
                 });
                 self.rest.push(t);
             }
         }
 
+        Ok(())
     }
 
-    fn build(mut self) -> FixedTimespanSet {
-        self.rest.sort_by(|a, b| a.0.cmp(&b.0));
+    /// Sorts and optimises the timespans, or returns `None` if there’s no
+    /// standard time timespan to use as the first one.
+    fn build(mut self) -> Option<FixedTimespanSet> {
+        self.rest.sort_by_key(|a| a.0);
 
         let first = match self.first {
             Some(ft) => ft,
-            None     => self.rest.iter().find(|t| t.1.dst_offset == 0).unwrap().1.clone(),
+            None     => self.rest.iter().find(|t| !t.1.is_dst)?.1.clone(),
         };
 
         let mut zoneset = FixedTimespanSet {
-            first: first,
+            first,
             rest:  self.rest,
         };
         optimise(&mut zoneset);
-        zoneset
+        Some(zoneset)
     }
 }
 
@@ -382,20 +797,20 @@ mod test {
     #[allow(unused_results)]
     fn optimise_macquarie() {
         let mut transitions = FixedTimespanSet {
-            first: FixedTimespan { utc_offset:     0, dst_offset:    0, name:  "zzz".to_owned() },
+            first: FixedTimespan { utc_offset:     0, dst_offset:    0, is_dst: false, name:  "zzz".to_owned() },
             rest: vec![
-                (-2_214_259_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (-1_680_508_800, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
-                (-1_669_892_400, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),  // gets removed
-                (-1_665_392_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (-1_601_719_200, FixedTimespan { utc_offset:     0,  dst_offset:    0,  name:  "zzz".to_owned() }),
-                (  -687_052_800, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (   -94_730_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),  // also gets removed
-                (   -71_136_000, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
-                (   -55_411_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (   -37_267_200, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
-                (   -25_776_000, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (    -5_817_600, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
+                (-2_214_259_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0, is_dst: false,  name: "AEST".to_owned() }),
+                (-1_680_508_800, FixedTimespan { utc_offset: 36000,  dst_offset: 3600, is_dst: true,   name: "AEDT".to_owned() }),
+                (-1_669_892_400, FixedTimespan { utc_offset: 36000,  dst_offset: 3600, is_dst: true,   name: "AEDT".to_owned() }),  // gets removed
+                (-1_665_392_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0, is_dst: false,  name: "AEST".to_owned() }),
+                (-1_601_719_200, FixedTimespan { utc_offset:     0,  dst_offset:    0, is_dst: false,  name:  "zzz".to_owned() }),
+                (  -687_052_800, FixedTimespan { utc_offset: 36000,  dst_offset:    0, is_dst: false,  name: "AEST".to_owned() }),
+                (   -94_730_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0, is_dst: false,  name: "AEST".to_owned() }),  // also gets removed
+                (   -71_136_000, FixedTimespan { utc_offset: 36000,  dst_offset: 3600, is_dst: true,   name: "AEDT".to_owned() }),
+                (   -55_411_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0, is_dst: false,  name: "AEST".to_owned() }),
+                (   -37_267_200, FixedTimespan { utc_offset: 36000,  dst_offset: 3600, is_dst: true,   name: "AEDT".to_owned() }),
+                (   -25_776_000, FixedTimespan { utc_offset: 36000,  dst_offset:    0, is_dst: false,  name: "AEST".to_owned() }),
+                (    -5_817_600, FixedTimespan { utc_offset: 36000,  dst_offset: 3600, is_dst: true,   name: "AEDT".to_owned() }),
             ],
         };
 
@@ -406,4 +821,328 @@ mod test {
         optimise(&mut transitions);
         assert_eq!(transitions, result);
     }
+
+    fn london() -> FixedTimespanSet {
+        FixedTimespanSet {
+            first: FixedTimespan { utc_offset: -75, dst_offset: 0, is_dst: false, name: "LMT".to_owned() },
+            rest: vec![
+                (-3_852_662_325, FixedTimespan { utc_offset: 0, dst_offset:    0, is_dst: false, name: "GMT".to_owned() }),
+                (-1_691_964_000, FixedTimespan { utc_offset: 0, dst_offset: 3600, is_dst: true,  name: "BST".to_owned() }),
+                (-1_680_472_800, FixedTimespan { utc_offset: 0, dst_offset:    0, is_dst: false, name: "GMT".to_owned() }),
+            ],
+        }
+    }
+
+    #[test]
+    fn find_utc() {
+        let set = london();
+        assert_eq!(set.find_utc(-4_000_000_000).name, "LMT");
+        assert_eq!(set.find_utc(-3_852_662_326).name, "LMT");
+        assert_eq!(set.find_utc(-3_852_662_325).name, "GMT");
+        assert_eq!(set.find_utc(-1_691_964_000).name, "BST");
+        assert_eq!(set.find_utc(-1_680_472_801).name, "BST");
+        assert_eq!(set.find_utc(0).name, "GMT");
+    }
+
+    #[test]
+    fn transitions_in() {
+        let set = london();
+        assert_eq!(set.transitions().count(), 3);
+
+        fn names(iter: slice::Iter<'_, (i64, FixedTimespan)>) -> Vec<&str> {
+            iter.map(|t| &*t.1.name).collect()
+        }
+
+        assert_eq!(names(set.transitions_in(-1_691_964_000 ..)), vec![ "BST", "GMT" ]);
+        assert_eq!(names(set.transitions_in(.. -1_691_964_000)), vec![ "GMT" ]);
+        assert_eq!(names(set.transitions_in(..= -1_691_964_000)), vec![ "GMT", "BST" ]);
+        assert_eq!(names(set.transitions_in(0 .. 10)), Vec::<&str>::new());
+    }
+
+    fn new_york_2024() -> FixedTimespanSet {
+        FixedTimespanSet {
+            first: FixedTimespan { utc_offset: -18000, dst_offset:    0, is_dst: false, name: "EST".to_owned() },
+            rest: vec![
+                (1_710_054_000, FixedTimespan { utc_offset: -18000, dst_offset: 3600, is_dst: true,  name: "EDT".to_owned() }),  // 2024-03-10 07:00
+                (1_730_613_600, FixedTimespan { utc_offset: -18000, dst_offset:    0, is_dst: false, name: "EST".to_owned() }),  // 2024-11-03 06:00
+            ],
+        }
+    }
+
+    #[test]
+    fn find_local_single() {
+        let set = new_york_2024();
+        let noon = 1_718_452_800;  // 2024-06-15 12:00 local
+        match set.find_local(noon) {
+            LocalResult::Single(m) => {
+                assert_eq!(m.timestamp, noon + 14400);
+                assert_eq!(m.timespan.name, "EDT");
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn find_local_gap() {
+        let set = new_york_2024();
+        let local = 1_710_037_800;  // 2024-03-10 02:30 local
+        match set.find_local(local) {
+            LocalResult::Gap(before, after) => {
+                assert_eq!((before.timestamp, &*before.timespan.name), (1_710_052_200, "EST"));
+                assert_eq!((after.timestamp,  &*after.timespan.name),  (1_710_055_800, "EDT"));
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        assert_eq!(set.resolve_local(local, Disambiguation::Earlier).unwrap().timestamp, 1_710_052_200);
+        assert_eq!(set.resolve_local(local, Disambiguation::Later).unwrap().timestamp, 1_710_055_800);
+        assert_eq!(set.resolve_local(local, Disambiguation::ShiftForward).unwrap().timestamp, 1_710_055_800);
+        assert_eq!(set.resolve_local(local, Disambiguation::Reject), None);
+
+        // 03:00 is the first local time after the gap.
+        assert_eq!(set.resolve_local(local + 1800, Disambiguation::Reject).unwrap().timestamp, 1_710_054_000);
+    }
+
+    #[test]
+    fn find_local_overlap() {
+        let set = new_york_2024();
+        let local = 1_730_597_400;  // 2024-11-03 01:30 local
+        match set.find_local(local) {
+            LocalResult::Ambiguous(earlier, later) => {
+                assert_eq!((earlier.timestamp, &*earlier.timespan.name), (1_730_611_800, "EDT"));
+                assert_eq!((later.timestamp,   &*later.timespan.name),   (1_730_615_400, "EST"));
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        assert_eq!(set.resolve_local(local, Disambiguation::Earlier).unwrap().timestamp, 1_730_611_800);
+        assert_eq!(set.resolve_local(local, Disambiguation::Later).unwrap().timestamp, 1_730_615_400);
+        assert_eq!(set.resolve_local(local, Disambiguation::ShiftForward).unwrap().timestamp, 1_730_611_800);
+        assert_eq!(set.resolve_local(local, Disambiguation::Reject), None);
+
+        // 02:00 only happens once, in standard time.
+        assert_eq!(set.resolve_local(local + 1800, Disambiguation::Reject).unwrap().timestamp, 1_730_617_200);
+    }
+
+    #[test]
+    fn find_local_offset_over_a_week() {
+        let set = FixedTimespanSet {
+            first: FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "Z".to_owned() },
+            rest: vec![
+                (946_684_800, FixedTimespan { utc_offset: 720_000, dst_offset: 0, is_dst: false, name: "BIG".to_owned() }),  // 2000-01-01 00:00
+            ],
+        };
+
+        match set.find_local(946_684_800 + 700_000) {
+            LocalResult::Gap(before, after) => {
+                assert_eq!((before.timestamp, &*before.timespan.name), (946_664_800, "Z"));
+                assert_eq!((after.timestamp,  &*after.timespan.name),  (947_384_800, "BIG"));
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        assert_eq!(set.resolve_local(946_684_800 + 720_000, Disambiguation::Reject).unwrap().timestamp, 946_684_800);
+    }
+
+    #[test]
+    fn find_local_extremes() {
+        let set = new_york_2024();
+        match set.find_local(i64::MAX) {
+            LocalResult::Single(m) => assert_eq!((m.timestamp, &*m.timespan.name), (i64::MAX, "EST")),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        match set.find_local(i64::MIN) {
+            LocalResult::Single(m) => assert_eq!((m.timestamp, &*m.timespan.name), (i64::MIN + 18000, "EST")),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    fn new_york() -> Table {
+        use line::LineParser;
+        use table::TableBuilder;
+
+        let parser = LineParser::new();
+        let mut builder = TableBuilder::new();
+        for line in &[
+            "Rule US 1967 2006 - Oct lastSun 2:00 0 S",
+            "Rule US 1967 2006 - Apr lastSun 2:00 1:00 D",
+            "Rule US 2007 max - Mar Sun>=8 2:00 1:00 D",
+            "Rule US 2007 max - Nov Sun>=1 2:00 0 S",
+            "Zone America/New_York -4:56:02 - LMT 1883 Nov 18 12:03:58",
+            "                      -5:00 US E%sT",
+        ] {
+            builder.add_line(parser.parse_str(line).unwrap()).unwrap();
+        }
+
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn default_years() {
+        let timespans = new_york().timespans("America/New_York").unwrap();
+        assert_eq!(timespans.rest.len(), 1 + (2099 - 1967 + 1) * 2);
+        assert_eq!(timespans.rest.last().unwrap().0, 4_097_196_000);  // 2099-11-01
+    }
+
+    #[test]
+    fn year_range() {
+        let options = TransitionOptions { start_year: 1970, end: TransitionsEnd::Year(2040) };
+        let timespans = new_york().timespans_with_options("America/New_York", &options).unwrap();
+        assert_eq!(timespans.rest[1].0, 9_961_200);  // 1970-04-26
+        assert_eq!(timespans.rest.last().unwrap().0, 2_235_621_600);  // 2040-11-04
+    }
+
+    #[test]
+    fn until_tz_string() {
+        let options = TransitionOptions { start_year: 1800, end: TransitionsEnd::UntilTzString(2099) };
+        let timespans = new_york().timespans_with_options("America/New_York", &options).unwrap();
+        assert_eq!(timespans.rest.last().unwrap().0, 1_225_605_600);  // 2008-11-02
+    }
+
+    #[test]
+    fn until_tz_string_without_years() {
+        let table = table(&[
+            "Rule X min max - Mar lastSun 1:00u 1:00 S",
+            "Rule X min max - Oct lastSun 1:00u 0 -",
+            "Zone Test/Always 0:00 X X%sT",
+        ]);
+
+        let options = TransitionOptions { start_year: 1970, end: TransitionsEnd::UntilTzString(1972) };
+        let timespans = table.timespans_with_options("Test/Always", &options).unwrap();
+        assert_eq!(timespans.rest.len(), 6);
+        assert_eq!(timespans.rest.last().unwrap().0, 89_168_400);  // 1972-10-29 01:00
+    }
+
+    #[test]
+    fn default_methods() {
+        struct Fixed;
+
+        impl TableTransitions for Fixed {
+            fn timespans_with_options(&self, zone_name: &str, options: &TransitionOptions) -> Option<FixedTimespanSet> {
+                if zone_name != "Etc/Fixed" {
+                    return None;
+                }
+
+                let first = FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: format!("FIX{}", options.start_year) };
+                Some(FixedTimespanSet { first, rest: Vec::new() })
+            }
+        }
+
+        let options = TransitionOptions { start_year: 2000, end: TransitionsEnd::Year(2001) };
+        assert_eq!(Fixed.try_timespans_with_options("Etc/Fixed", &options).unwrap().first.name, "FIX2000");
+        assert_eq!(Fixed.timespans("Etc/Fixed").unwrap().first.name, format!("FIX{}", TransitionOptions::default().start_year));
+        assert_eq!(Fixed.try_timespans("Etc/Other"), Err(Error::UnknownZone("Etc/Other".to_owned())));
+    }
+
+    fn table(lines: &[&str]) -> Table {
+        use line::LineParser;
+        use table::TableBuilder;
+
+        let parser = LineParser::new();
+        let mut builder = TableBuilder::new();
+        for line in lines {
+            builder.add_line(parser.parse_str(line).unwrap()).unwrap();
+        }
+
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn try_unknown() {
+        let mut table = new_york();
+        assert_eq!(table.try_timespans("America/Boston"), Err(Error::UnknownZone("America/Boston".to_owned())));
+        assert_eq!(table.timespans("America/Boston"), None);
+
+        table.rulesets.clear();
+        let error = table.try_timespans("America/New_York").unwrap_err();
+        assert_eq!(error, Error::UnknownRuleset { zone: "America/New_York".to_owned(), line: 2, ruleset: "US".to_owned() });
+        assert_eq!(error.to_string(), "line 2 of zone America/New_York refers to unknown ruleset \"US\"");
+    }
+
+    #[test]
+    fn negative_start_year() {
+        let table = table(&[
+            "Rule X min max - Mar lastSun 2:00 1:00 D",
+            "Rule X min max - Oct lastSun 2:00 0    S",
+            "Zone Etc/Test 0:00 X T%sT",
+        ]);
+
+        let options = TransitionOptions { start_year: -10, end: TransitionsEnd::Year(10) };
+        let timespans = table.try_timespans_with_options("Etc/Test", &options).unwrap();
+        assert_eq!(timespans.rest.len(), 42);
+    }
+
+    #[test]
+    fn try_missing_until() {
+        let table = table(&[
+            "Zone Europe/London 0:00 - GMT",
+            "                   1:00 - BST",
+        ]);
+        assert_eq!(table.try_timespans("Europe/London"), Err(Error::MissingUntil { zone: "Europe/London".to_owned(), line: 1 }));
+    }
+
+    #[test]
+    fn try_invalid_until() {
+        let table = table(&[
+            "Zone Europe/London 0:00 - GMT 1968 Feb 30",
+            "                   1:00 - BST",
+        ]);
+        assert_eq!(table.try_timespans("Europe/London"), Err(Error::InvalidUntil { zone: "Europe/London".to_owned(), line: 1 }));
+    }
+
+    #[test]
+    fn try_invalid_rule_day() {
+        let table = table(&[
+            "Rule Leap 2000 2010 - Feb 29 2:00 1:00 S",
+            "Zone Europe/London 0:00 - GMT 1990",
+            "                   0:00 Leap GMT/BST",
+        ]);
+        assert_eq!(table.try_timespans("Europe/London"), Err(Error::InvalidRuleDay { zone: "Europe/London".to_owned(), line: 2, ruleset: "Leap".to_owned(), year: 2001 }));
+
+        // It’s fine as long as only leap years get looked at.
+        let options = TransitionOptions { start_year: 2004, end: TransitionsEnd::Year(2004) };
+        assert!(table.try_timespans_with_options("Europe/London", &options).is_ok());
+    }
+
+    #[test]
+    fn try_no_standard_time() {
+        let table = table(&[
+            "Rule Always 1970 only - Jan 1 0:00 1:00 S",
+            "Zone Europe/London 0:00 Always GMT/BST",
+        ]);
+        assert_eq!(table.try_timespans("Europe/London"), Err(Error::NoStandardTime { zone: "Europe/London".to_owned() }));
+    }
+
+    #[test]
+    fn minimum_from_year() {
+        let table = table(&[
+            "Rule Old min 1900 - Jul 1 0:00 1:00 S",
+            "Rule Old min 1900 - Dec 1 0:00 0 -",
+            "Zone Europe/London 0:00 Old GMT/BST",
+        ]);
+
+        let options = TransitionOptions { start_year: 1899, end: TransitionsEnd::Year(2099) };
+        let timespans = table.try_timespans_with_options("Europe/London", &options).unwrap();
+        let names = timespans.rest.iter().map(|t| &*t.1.name).collect::<Vec<_>>();
+        assert_eq!(names, vec![ "BST", "GMT", "BST", "GMT" ]);
+        assert_eq!(timespans.rest[3].0, -2_180_134_800);  // 1900-11-30 23:00
+    }
+
+    #[test]
+    fn explicit_dst_flag() {
+        let table = table(&[
+            "Rule Flag 2000 only - Mar 1 0:00 1:00s -",
+            "Rule Flag 2000 only - Oct 1 0:00 0d -",
+            "Zone Europe/London 0:00 Flag A/B",
+        ]);
+
+        let timespans = table.timespans("Europe/London").unwrap();
+
+        let summer = timespans.find_utc(959_817_600);  // 2000-06-01
+        assert_eq!((summer.dst_offset, summer.is_dst, &*summer.name), (3600, false, "A"));
+
+        let winter = timespans.find_utc(975_628_800);  // 2000-12-01
+        assert_eq!((winter.dst_offset, winter.is_dst, &*winter.name), (0, true, "B"));
+    }
 }
@@ -0,0 +1,528 @@
+//! Writing timespan sets out as TZif files.
+//!
+//! TZif is the binary format that zic produces, and that libc (and most
+//! other date and time libraries) read out of `/usr/share/zoneinfo`. It’s
+//! described in RFC 8536. A TZif file is made up of:
+//!
+//! 1. A header and data block using 32-bit timestamps, which is all a
+//!    version 1 file contains;
+//! 2. For version 2 and later, a second header and data block using 64-bit
+//!    timestamps;
+//! 3. For version 2 and later, a *footer* holding a POSIX TZ string that
+//!    describes the transitions after the last one in the data block.
+//!
+//! Each data block has a list of transition times, the index of the local
+//! time type that each transition switches to, the local time types
+//! themselves (UTC offset, DST flag, and abbreviation), the abbreviations
+//! as a string table, any leap second corrections, and the standard/wall
+//! and UT/local indicators for each type.
+//!
+//! Versions 3 and 4 have the same layout as version 2. Version 3 allows the
+//! footer to use the POSIX TZ string extensions (hours beyond 24 in the
+//! transition times, and DST all year round), and version 4 allows the leap
+//! second table to end with an expiry time. It’s the caller’s job to only
+//! ask for a version that can hold the footer that’s been given.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use parse_zoneinfo::posix::TableTzString;
+//! use parse_zoneinfo::table::Table;
+//! use parse_zoneinfo::transitions::TableTransitions;
+//! use parse_zoneinfo::tzif::TzifWriter;
+//!
+//! let table = Table::from_tzdata_dir("tzdata").unwrap();
+//! let timespans = table.timespans("Europe/London").unwrap();
+//! let footer = table.tz_string("Europe/London").unwrap().unwrap();
+//!
+//! let file = File::create("London").unwrap();
+//! TzifWriter::new(footer.minimum_version)
+//!     .footer(&footer.string)
+//!     .write(&timespans, file)
+//!     .unwrap();
+//! ```
+
+use std::io::{self, Write};
+
+use leap::LeapSecondTable;
+use transitions::{FixedTimespan, FixedTimespanSet};
+
+
+/// The version of the TZif format to write.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum Version {
+
+    /// The original format, with 32-bit timestamps and no footer.
+    V1,
+
+    /// Adds 64-bit timestamps and a POSIX TZ string footer.
+    V2,
+
+    /// Allows the POSIX TZ string extensions in the footer.
+    V3,
+
+    /// Allows the leap second table to be truncated, and to end with an
+    /// expiry time.
+    V4,
+}
+
+impl Version {
+    fn header_byte(self) -> u8 {
+        match self {
+            Version::V1 => 0,
+            Version::V2 => b'2',
+            Version::V3 => b'3',
+            Version::V4 => b'4',
+        }
+    }
+}
+
+
+/// Writes `FixedTimespanSet` values out in the TZif format.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TzifWriter<'a> {
+    version: Version,
+    footer: Option<String>,
+    leap_seconds: Option<&'a LeapSecondTable>,
+}
+
+impl<'a> TzifWriter<'a> {
+
+    /// Creates a writer for the given version, with an empty footer and no
+    /// leap seconds.
+    pub fn new(version: Version) -> TzifWriter<'a> {
+        TzifWriter {
+            version,
+            footer: None,
+            leap_seconds: None,
+        }
+    }
+
+    /// Sets the POSIX TZ string to write in the footer. This is ignored for
+    /// version 1 files, which don’t have one.
+    pub fn footer(mut self, tz_string: &str) -> TzifWriter<'a> {
+        self.footer = Some(tz_string.to_owned());
+        self
+    }
+
+    /// Sets the leap seconds to correct for, as is done for the zones in
+    /// the “right/” directory. Transition times get adjusted to count leap
+    /// seconds, and the corrections get written to the leap second table.
+    pub fn leap_seconds(mut self, leap_seconds: &'a LeapSecondTable) -> TzifWriter<'a> {
+        self.leap_seconds = Some(leap_seconds);
+        self
+    }
+
+    /// Writes the given timespan set to a vector of bytes.
+    pub fn to_bytes(&self, timespans: &FixedTimespanSet) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write(timespans, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Writes the given timespan set to the given writer.
+    ///
+    /// Returns an error if the writer fails, if the footer isn’t a valid
+    /// TZ string for a file (because it contains a newline), or if the
+    /// timespans don’t fit in the format: there can be at most 256 distinct
+    /// local time types, every abbreviation has to start within the first
+    /// 256 bytes of the string table, and every offset has to fit in a
+    /// 32-bit integer.
+    pub fn write<W: Write>(&self, timespans: &FixedTimespanSet, mut w: W) -> io::Result<()> {
+        if let Some(ref footer) = self.footer {
+            if footer.contains('\n') {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "TZ string footer contains a newline"));
+            }
+        }
+
+        let leaps = self.leap_records();
+        let transitions = timespans.rest.iter()
+            .map(|&(time, ref span)| (correct(time, &leaps), span))
+            .collect::<Vec<_>>();
+
+        let block = DataBlock::new(&timespans.first, &transitions, &leaps, i64::from(i32::MIN), i64::from(i32::MAX))?;
+        block.write(self.version, 4, &mut w)?;
+
+        if self.version >= Version::V2 {
+            let block = DataBlock::new(&timespans.first, &transitions, &leaps, i64::MIN, i64::MAX)?;
+            block.write(self.version, 8, &mut w)?;
+
+            w.write_all(b"\n")?;
+            if let Some(ref footer) = self.footer {
+                w.write_all(footer.as_bytes())?;
+            }
+            w.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// Computes the leap second records, which hold the time each leap
+    /// second occurs (counting all the previous leap seconds) along with
+    /// the total correction after it.
+    fn leap_records(&self) -> Vec<(i64, i64)> {
+        let table = match self.leap_seconds {
+            Some(table) => table,
+            None        => return Vec::new(),
+        };
+
+        let mut total = 0;
+        let mut records = Vec::new();
+        for leap_second in &table.leap_seconds {
+            let occurrence = leap_second.timestamp + total;
+            total += leap_second.correction;
+            records.push((occurrence, total));
+        }
+
+        // Version 4 lets the table end with a record that doesn’t change
+        // the correction, which marks when the data expires.
+        if let Some(expires) = table.expires {
+            if self.version >= Version::V4 {
+                records.push((expires + total, total));
+            }
+        }
+
+        records
+    }
+}
+
+/// Adjusts a Unix timestamp to count the leap seconds before it.
+fn correct(time: i64, leaps: &[(i64, i64)]) -> i64 {
+    let mut previous = 0;
+    for &(occurrence, total) in leaps {
+        if time <= occurrence - total {
+            break;
+        }
+
+        previous = total;
+    }
+
+    time + previous
+}
+
+
+/// A local time type record.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct LocalTimeType {
+    utoff: i32,
+    is_dst: bool,
+    abbreviation_index: u8,
+}
+
+/// The contents of one data block, with all its timestamps within a range.
+struct DataBlock<'a> {
+    transition_times: Vec<i64>,
+    transition_types: Vec<u8>,
+    types: Vec<LocalTimeType>,
+    abbreviations: Vec<u8>,
+    leaps: Vec<(i64, i64)>,
+    spans: Vec<&'a FixedTimespan>,
+}
+
+impl<'a> DataBlock<'a> {
+    fn new(first: &'a FixedTimespan, transitions: &[(i64, &'a FixedTimespan)], leaps: &[(i64, i64)], min: i64, max: i64) -> io::Result<DataBlock<'a>> {
+
+        // Type 0 has to be the one in effect before the first transition
+        // in the block, which may not be the first of all if some of the
+        // transitions are too early to be written.
+        let initial = transitions.iter()
+            .rev()
+            .find(|&&(time, _)| time < min)
+            .map_or(first, |&(_, span)| span);
+
+        let mut block = DataBlock {
+            transition_times: Vec::new(),
+            transition_types: Vec::new(),
+            types: Vec::new(),
+            abbreviations: Vec::new(),
+            leaps: leaps.iter().cloned().filter(|&(time, _)| time >= min && time <= max).collect(),
+            spans: Vec::new(),
+        };
+
+        let _ = block.type_index(initial)?;
+        for &(time, span) in transitions {
+            if time >= min && time <= max {
+                let index = block.type_index(span)?;
+                block.transition_times.push(time);
+                block.transition_types.push(index);
+            }
+        }
+
+        Ok(block)
+    }
+
+    /// Returns the index of the type for the given timespan, adding it if
+    /// there isn’t one already. Type indices are stored in a byte, so
+    /// there can’t be more than 256 types.
+    fn type_index(&mut self, span: &'a FixedTimespan) -> io::Result<u8> {
+        let equivalent = |other: &&FixedTimespan| {
+            other.total_offset() == span.total_offset()
+                && other.is_dst == span.is_dst
+                && other.name == span.name
+        };
+
+        if let Some(index) = self.spans.iter().position(equivalent) {
+            return Ok(index as u8);
+        }
+
+        // The offset can’t be the smallest 32-bit integer, as negating it
+        // would overflow.
+        let utoff = match span.total_offset() {
+            offset if offset > i64::from(i32::MIN) && offset <= i64::from(i32::MAX) => offset as i32,
+            _ => return Err(invalid_input("UTC offset does not fit in 32 bits")),
+        };
+
+        if self.types.len() > usize::from(u8::MAX) {
+            return Err(invalid_input("more than 256 local time types"));
+        }
+
+        let abbreviation_index = self.abbreviation_index(&span.name)?;
+        self.types.push(LocalTimeType {
+            utoff,
+            is_dst: span.is_dst,
+            abbreviation_index,
+        });
+        self.spans.push(span);
+        Ok((self.types.len() - 1) as u8)
+    }
+
+    /// Returns the index of the given abbreviation in the string table,
+    /// adding it if it isn’t there already. The index is stored in a byte,
+    /// so every abbreviation has to start within the first 256 bytes.
+    fn abbreviation_index(&mut self, name: &str) -> io::Result<u8> {
+        let mut start = 0;
+        for (i, &byte) in self.abbreviations.iter().enumerate() {
+            if byte == 0 {
+                if &self.abbreviations[start .. i] == name.as_bytes() {
+                    return Ok(start as u8);
+                }
+
+                start = i + 1;
+            }
+        }
+
+        let index = self.abbreviations.len();
+        if index > usize::from(u8::MAX) {
+            return Err(invalid_input("abbreviations do not fit in 256 bytes"));
+        }
+
+        self.abbreviations.extend_from_slice(name.as_bytes());
+        self.abbreviations.push(0);
+        Ok(index as u8)
+    }
+
+    fn write<W: Write>(&self, version: Version, time_size: usize, w: &mut W) -> io::Result<()> {
+        let type_count = self.types.len() as u32;
+
+        w.write_all(b"TZif")?;
+        w.write_all(&[version.header_byte()])?;
+        w.write_all(&[0; 15])?;
+        for &count in &[type_count, type_count, self.leaps.len() as u32, self.transition_times.len() as u32, type_count, self.abbreviations.len() as u32] {
+            w.write_all(&count.to_be_bytes())?;
+        }
+
+        for &time in &self.transition_times {
+            write_time(w, time, time_size)?;
+        }
+
+        w.write_all(&self.transition_types)?;
+
+        for t in &self.types {
+            w.write_all(&t.utoff.to_be_bytes())?;
+            w.write_all(&[t.is_dst as u8, t.abbreviation_index])?;
+        }
+
+        w.write_all(&self.abbreviations)?;
+
+        for &(occurrence, correction) in &self.leaps {
+            write_time(w, occurrence, time_size)?;
+            w.write_all(&(correction as i32).to_be_bytes())?;
+        }
+
+        // Every time is treated as local wall clock time.
+        w.write_all(&vec![0; self.types.len()])?;
+        w.write_all(&vec![0; self.types.len()])?;
+        Ok(())
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn write_time<W: Write>(w: &mut W, time: i64, size: usize) -> io::Result<()> {
+    if size == 4 {
+        w.write_all(&(time as i32).to_be_bytes())
+    }
+    else {
+        w.write_all(&time.to_be_bytes())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use leap::LeapSecond;
+    use line::LeapType;
+
+    fn span(utc_offset: i64, dst_offset: i64, name: &str) -> FixedTimespan {
+        FixedTimespan { utc_offset, dst_offset, is_dst: dst_offset != 0, name: name.to_owned() }
+    }
+
+    fn london() -> FixedTimespanSet {
+        FixedTimespanSet {
+            first: span(-75, 0, "LMT"),
+            rest: vec![
+                (-3_852_662_325, span(0, 0, "GMT")),
+                (   -37_238_400, span(0, 3600, "BST")),
+                (   -25_747_200, span(0, 0, "GMT")),
+            ],
+        }
+    }
+
+    fn read_u32(bytes: &[u8], at: usize) -> u32 {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(&bytes[at .. at + 4]);
+        u32::from_be_bytes(buf)
+    }
+
+    fn read_i64(bytes: &[u8], at: usize) -> i64 {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(&bytes[at .. at + 8]);
+        i64::from_be_bytes(buf)
+    }
+
+    /// Returns the counts from the header at the given position, and the
+    /// length of the header plus its data block.
+    fn header(bytes: &[u8], at: usize, time_size: usize) -> ([u32; 6], usize) {
+        assert_eq!(&bytes[at .. at + 4], b"TZif");
+        let mut counts = [0; 6];
+        for (i, count) in counts.iter_mut().enumerate() {
+            *count = read_u32(bytes, at + 20 + i * 4);
+        }
+
+        let [isut, isstd, leap, time, typ, chr] = counts;
+        let length = 44 + time as usize * (time_size + 1) + typ as usize * 6 + chr as usize
+                   + leap as usize * (time_size + 4) + isstd as usize + isut as usize;
+        (counts, length)
+    }
+
+    #[test]
+    fn version_1() {
+        let bytes = TzifWriter::new(Version::V1).footer("GMT0").to_bytes(&london()).unwrap();
+        assert_eq!(bytes[4], 0);
+
+        // The first transition is too early for 32 bits, so GMT becomes
+        // the initial type.
+        let (counts, length) = header(&bytes, 0, 4);
+        assert_eq!(counts, [2, 2, 0, 2, 2, 8]);
+        assert_eq!(length, bytes.len());
+        assert_eq!(&bytes[44 + 8 + 2 + 12 ..][.. 8], b"GMT\0BST\0");
+    }
+
+    #[test]
+    fn version_2() {
+        let bytes = TzifWriter::new(Version::V2).footer("GMT0BST,M3.5.0/1,M10.5.0").to_bytes(&london()).unwrap();
+        assert_eq!(bytes[4], b'2');
+
+        let (_, v1_length) = header(&bytes, 0, 4);
+        let (counts, v2_length) = header(&bytes, v1_length, 8);
+        assert_eq!(bytes[v1_length + 4], b'2');
+        assert_eq!(counts, [3, 3, 0, 3, 3, 12]);
+
+        let data = v1_length + 44;
+        assert_eq!(read_i64(&bytes, data), -3_852_662_325);
+        assert_eq!(read_i64(&bytes, data + 16), -25_747_200);
+        assert_eq!(&bytes[data + 24 .. data + 27], &[1, 2, 1]);
+
+        let first_type = data + 27;
+        assert_eq!(read_u32(&bytes, first_type) as i32, -75);
+        assert_eq!(&bytes[first_type + 4 .. first_type + 6], &[0, 0]);
+        assert_eq!(&bytes[first_type + 10 .. first_type + 12], &[0, 4]);
+        assert_eq!(read_u32(&bytes, first_type + 12), 3600);
+        assert_eq!(&bytes[first_type + 16 .. first_type + 18], &[1, 8]);
+        assert_eq!(&bytes[first_type + 18 .. first_type + 30], b"LMT\0GMT\0BST\0");
+
+        assert_eq!(&bytes[v1_length + v2_length ..], b"\nGMT0BST,M3.5.0/1,M10.5.0\n");
+    }
+
+    #[test]
+    fn bad_footer() {
+        assert!(TzifWriter::new(Version::V2).footer("GMT0\n").to_bytes(&london()).is_err());
+    }
+
+    #[test]
+    fn too_many_types() {
+        let spans = |count: i64| FixedTimespanSet {
+            first: span(0, 0, "A"),
+            rest: (1 .. count).map(|i| (i * 86400, span(i, 0, "A"))).collect(),
+        };
+
+        assert!(TzifWriter::new(Version::V2).to_bytes(&spans(256)).is_ok());
+        let error = TzifWriter::new(Version::V2).to_bytes(&spans(257)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn too_many_abbreviations() {
+        // Each of these takes up eight bytes of the string table, so the
+        // 33rd starts at byte 256.
+        let spans = |count: i64| FixedTimespanSet {
+            first: span(0, 0, "ABC0000"),
+            rest: (1 .. count).map(|i| (i * 86400, span(0, 0, &format!("ABC{:04}", i)))).collect(),
+        };
+
+        assert!(TzifWriter::new(Version::V2).to_bytes(&spans(32)).is_ok());
+        let error = TzifWriter::new(Version::V2).to_bytes(&spans(33)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn offset_too_large() {
+        let set = FixedTimespanSet {
+            first: span(0, 0, "GMT"),
+            rest: vec![ (0, span(i64::from(i32::MAX), 3600, "BIG")) ],
+        };
+
+        let error = TzifWriter::new(Version::V2).to_bytes(&set).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let set = FixedTimespanSet { first: span(i64::from(i32::MIN), 0, "MIN"), rest: Vec::new() };
+        assert!(TzifWriter::new(Version::V2).to_bytes(&set).is_err());
+    }
+
+    #[test]
+    fn leap_seconds() {
+        let table = LeapSecondTable {
+            leap_seconds: vec![
+                LeapSecond { timestamp: 78_796_800, correction: 1, leap_type: LeapType::Stationary },
+                LeapSecond { timestamp: 94_694_400, correction: 1, leap_type: LeapType::Stationary },
+            ],
+            expires: Some(100_000_000),
+        };
+
+        let set = FixedTimespanSet {
+            first: span(0, 0, "GMT"),
+            rest: vec![ (90_000_000, span(3600, 0, "CET")), (100_000_000, span(0, 0, "GMT")) ],
+        };
+
+        let bytes = TzifWriter::new(Version::V4).leap_seconds(&table).to_bytes(&set).unwrap();
+        let (_, v1_length) = header(&bytes, 0, 4);
+        let (counts, _) = header(&bytes, v1_length, 8);
+        assert_eq!(counts[2], 3);
+
+        let data = v1_length + 44;
+        assert_eq!(read_i64(&bytes, data), 90_000_001);
+        assert_eq!(read_i64(&bytes, data + 8), 100_000_002);
+
+        let leaps = data + 2 * 9 + 2 * 6 + 8;
+        assert_eq!(read_i64(&bytes, leaps), 78_796_800);
+        assert_eq!(read_u32(&bytes, leaps + 8), 1);
+        assert_eq!(read_i64(&bytes, leaps + 12), 94_694_401);
+        assert_eq!(read_u32(&bytes, leaps + 20), 2);
+        assert_eq!(read_i64(&bytes, leaps + 24), 100_000_002);
+        assert_eq!(read_u32(&bytes, leaps + 32), 2);
+    }
+}
@@ -0,0 +1,256 @@
+//! Checking a built Table for data that zic would reject.
+//!
+//! The `TableBuilder` checks each line on its own as it gets added, but a
+//! lot of problems only show up when the lines are looked at together: a
+//! zone whose lines end out of order, a rule that stops before it starts,
+//! or a ruleset that nothing uses. zic checks for these while it compiles
+//! the data, and some of them would cause panics later on while generating
+//! transitions, so `Table::validate` checks for them up front.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use parse_zoneinfo::table::Table;
+//!
+//! let table = Table::from_tzdata_dir("tzdata").unwrap();
+//! for problem in table.validate() {
+//!     eprintln!("{:?}: {}", problem.severity(), problem);
+//! }
+//! ```
+
+use std::fmt;
+
+use line::{ChangeTime, Month, TimeSpecAndType, Year};
+use table::{Table, Saving};
+
+
+/// How serious a problem is.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum Severity {
+
+    /// The data is allowed, but is probably a mistake.
+    Warning,
+
+    /// The data isn’t allowed, and transitions can’t be generated from it.
+    Error,
+}
+
+
+/// A problem found by `Table::validate`. Zone lines are numbered from 1,
+/// with the zone line itself as line 1 and its continuation lines after
+/// it; rules are numbered from 1 in the order they were added.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Problem {
+
+    /// A zone line’s UNTIL time isn’t after the UNTIL time of the line
+    /// before it.
+    UntilNotIncreasing { zone: String, line: usize },
+
+    /// The last line of a zone has an UNTIL time, so there’s nothing saying
+    /// what happens after it.
+    UntilOnLastLine { zone: String },
+
+    /// A zone line’s UNTIL time uses `minimum` or `maximum` as its year,
+    /// or names a day that doesn’t exist.
+    InvalidUntil { zone: String, line: usize },
+
+    /// A rule’s FROM year comes after its TO year.
+    FromAfterTo { ruleset: String, rule: usize },
+
+    /// A ruleset isn’t referred to by any zone.
+    UnusedRuleset { ruleset: String },
+
+    /// A link has the same name as a zone, so the link gets ignored.
+    ShadowedLink { name: String },
+}
+
+impl Problem {
+
+    /// Returns how serious this problem is.
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Problem::UnusedRuleset { .. } | Problem::ShadowedLink { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::UntilNotIncreasing { ref zone, line }  => write!(f, "line {} of zone {} does not end after the line before it", line, zone),
+            Problem::UntilOnLastLine { ref zone }            => write!(f, "last line of zone {} has an UNTIL time", zone),
+            Problem::InvalidUntil { ref zone, line }        => write!(f, "line {} of zone {} has an invalid UNTIL time", line, zone),
+            Problem::FromAfterTo { ref ruleset, rule }      => write!(f, "rule {} of ruleset {} has a FROM year after its TO year", rule, ruleset),
+            Problem::UnusedRuleset { ref ruleset }          => write!(f, "ruleset {} is never used", ruleset),
+            Problem::ShadowedLink { ref name }              => write!(f, "link {} has the same name as a zone", name),
+        }
+    }
+}
+
+
+impl Table {
+
+    /// Checks the table for data that zic would reject, or that would stop
+    /// transitions from being generated. Returns every problem found,
+    /// ordered by the name of the zone, ruleset, or link it was found in.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        let mut zone_names = self.zonesets.keys().collect::<Vec<_>>();
+        zone_names.sort();
+
+        for name in zone_names {
+            let zoneset = &self.zonesets[name];
+            let mut previous = None;
+
+            for (i, info) in zoneset.iter().enumerate() {
+                let end_time = match info.end_time {
+                    Some(end_time) => end_time,
+                    None           => continue,
+                };
+
+                if i == zoneset.len() - 1 {
+                    problems.push(Problem::UntilOnLastLine { zone: name.clone() });
+                }
+
+                let key = match until_key(end_time) {
+                    Some(key) => key,
+                    None      => {
+                        problems.push(Problem::InvalidUntil { zone: name.clone(), line: i + 1 });
+                        continue;
+                    },
+                };
+
+                if matches!(previous, Some(p) if key <= p) {
+                    problems.push(Problem::UntilNotIncreasing { zone: name.clone(), line: i + 1 });
+                }
+
+                previous = Some(key);
+            }
+        }
+
+        let mut ruleset_names = self.rulesets.keys().collect::<Vec<_>>();
+        ruleset_names.sort();
+
+        for name in ruleset_names {
+            for (i, rule) in self.rulesets[name].iter().enumerate() {
+                if rule.years.is_empty() {
+                    problems.push(Problem::FromAfterTo { ruleset: name.clone(), rule: i + 1 });
+                }
+            }
+
+            let used = self.zonesets.values().flat_map(|zoneset| zoneset.iter()).any(|info| match info.saving {
+                Saving::Multiple(ref ruleset) => ruleset == name,
+                _                             => false,
+            });
+
+            if !used {
+                problems.push(Problem::UnusedRuleset { ruleset: name.clone() });
+            }
+        }
+
+        let mut link_names = self.links.keys().filter(|name| self.zonesets.contains_key(*name)).collect::<Vec<_>>();
+        link_names.sort();
+        problems.extend(link_names.into_iter().map(|name| Problem::ShadowedLink { name: name.clone() }));
+
+        problems
+    }
+}
+
+/// Returns an UNTIL time as a tuple that can be compared with others, or
+/// `None` if its year isn’t a number or its day isn’t valid. The time
+/// type is ignored, so two times within a day of each other that use
+/// different types may be compared wrongly.
+pub(crate) fn until_key(time: ChangeTime) -> Option<(i64, u8, i8, i64)> {
+    let (year, month, day, time) = match time {
+        ChangeTime::UntilYear(year)                => (year, Month::January, None, None),
+        ChangeTime::UntilMonth(year, month)        => (year, month, None, None),
+        ChangeTime::UntilDay(year, month, day)     => (year, month, Some(day), None),
+        ChangeTime::UntilTime(year, month, day, t) => (year, month, Some(day), Some(t)),
+    };
+
+    let year = match year {
+        Year::Number(year) => year,
+        _                  => return None,
+    };
+
+    let (year, month, day) = match day {
+        Some(day) => day.checked_concrete_date(year, month)?,
+        None      => (year, month, 1),
+    };
+
+    let seconds = time.map_or(0, |TimeSpecAndType(time, _)| time.as_seconds());
+    Some((year, month as u8, day, seconds))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use line::LineParser;
+    use table::TableBuilder;
+
+    fn validate(lines: &[&str]) -> Vec<Problem> {
+        let parser = LineParser::new();
+        let mut builder = TableBuilder::new();
+        for line in lines {
+            builder.add_line(parser.parse_str(line).unwrap()).unwrap();
+        }
+
+        builder.build().unwrap().validate()
+    }
+
+    #[test]
+    fn valid() {
+        let problems = validate(&[
+            "Rule GB-Eire 1916 only - May 21 2:00s 1:00 BST",
+            "Zone Europe/London -0:01:15 - LMT 1847 Dec 1",
+            "                   0:00 GB-Eire %s 1968 Oct 27",
+            "                   1:00 - BST",
+        ]);
+        assert_eq!(problems, Vec::new());
+    }
+
+    #[test]
+    fn zone_problems() {
+        let problems = validate(&[
+            "Zone Europe/London -0:01:15 - LMT 1847 Dec 1",
+            "                   0:00 - GMT 1847 Dec 1",
+            "                   1:00 - BST 1968 Feb 30",
+            "                   1:00 - BST 1971 Oct 31 2:00u",
+        ]);
+        assert_eq!(problems, vec![
+            Problem::UntilNotIncreasing { zone: "Europe/London".to_owned(), line: 2 },
+            Problem::InvalidUntil { zone: "Europe/London".to_owned(), line: 3 },
+            Problem::UntilOnLastLine { zone: "Europe/London".to_owned() },
+        ]);
+    }
+
+    #[test]
+    fn rule_problems() {
+        let problems = validate(&[
+            "Rule Backwards 2000 1990 - Mar lastSun 1:00u 1:00 S",
+            "Rule Backwards max 1990 - Oct lastSun 1:00u 0 -",
+            "Rule Unused 2000 only - Mar lastSun 1:00u 1:00 S",
+            "Zone Europe/London 0:00 Backwards GMT/BST",
+        ]);
+        assert_eq!(problems, vec![
+            Problem::FromAfterTo { ruleset: "Backwards".to_owned(), rule: 1 },
+            Problem::FromAfterTo { ruleset: "Backwards".to_owned(), rule: 2 },
+            Problem::UnusedRuleset { ruleset: "Unused".to_owned() },
+        ]);
+        assert_eq!(problems[2].severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn shadowed_link() {
+        let problems = validate(&[
+            "Zone Europe/London 0:00 - GMT",
+            "Zone Europe/Belfast 0:00 - GMT",
+            "Link Europe/London Europe/Belfast",
+        ]);
+        assert_eq!(problems, vec![ Problem::ShadowedLink { name: "Europe/Belfast".to_owned() } ]);
+        assert_eq!(problems[0].to_string(), "link Europe/Belfast has the same name as a zone");
+    }
+}